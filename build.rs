@@ -10,6 +10,8 @@ use std::env;
 use std::fs::File;
 use std::io::Error;
 use std::path::PathBuf;
+#[cfg(feature = "embedded-fortunes")]
+use std::{fs, path::Path};
 
 include!("src/cli.rs");
 
@@ -19,6 +21,9 @@ fn main() -> Result<(), Error> {
         Some(outdir) => outdir,
     };
 
+    #[cfg(feature = "embedded-fortunes")]
+    embed_collections(Path::new(&real_outdir))?;
+
     let outdir = match env::var_os("MAN_OUT") {
         None => real_outdir,
         Some(outdir) => outdir,
@@ -38,3 +43,44 @@ fn main() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Compresses the collections to embed into `OUT_DIR/collections.deflate`.
+///
+/// The archive is read back by `Embedded::parse` in `src/collection.rs`: each
+/// file is a big-endian `u32` length and its path, such as `fortunes/fedi`,
+/// followed by a `u32` length and its contents.
+#[cfg(feature = "embedded-fortunes")]
+fn embed_collections(outdir: &Path) -> Result<(), Error> {
+    let mut collections = vec!["fortunes"];
+    if cfg!(feature = "embedded-off") {
+        collections.push("off");
+    }
+
+    let mut archive = vec![];
+    for collection in collections {
+        println!("cargo:rerun-if-changed={collection}");
+        let mut files = fs::read_dir(collection)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<PathBuf>, Error>>()?;
+        files.sort();
+
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            if !file.is_file() || name.starts_with('.') || name.ends_with(".dat") {
+                continue;
+            }
+            let name = format!("{collection}/{name}");
+            for chunk in [name.into_bytes(), fs::read(&file)?] {
+                archive.extend((chunk.len() as u32).to_be_bytes());
+                archive.extend(chunk);
+            }
+        }
+    }
+
+    // Emitting rerun-if-changed replaces the default of rerunning on any change.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/cli.rs");
+
+    let compressed = miniz_oxide::deflate::compress_to_vec(&archive, 10);
+    fs::write(outdir.join("collections.deflate"), compressed)
+}
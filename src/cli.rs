@@ -4,7 +4,7 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use clap::{command, crate_authors, Arg, ArgAction, Command};
+use clap::{command, crate_authors, Arg, ArgAction, ArgGroup, Command};
 
 /// Builds the command line interface configuration.
 ///
@@ -21,9 +21,76 @@ use clap::{command, crate_authors, Arg, ArgAction, Command};
 /// let matches = cmd.clone().try_get_matches_from(vec!["app", "-ss"]).unwrap();
 /// assert_eq!(matches.get_count("short"), 2);
 ///
+/// // Test maximum length argument
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "-n", "80"]).unwrap();
+/// assert_eq!(matches.get_one::<usize>("length"), Some(&80));
+///
 /// // Test positional path argument
-/// let matches = cmd.try_get_matches_from(vec!["app", "my_custom_fortunes"]).unwrap();
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "my_custom_fortunes"]).unwrap();
 /// assert_eq!(matches.get_one::<String>("path").map(|s| s.as_str()), Some("my_custom_fortunes"));
+///
+/// // Test weighted positional paths
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "30%", "fedi", "pratchett"]).unwrap();
+/// assert_eq!(matches.get_many::<String>("path").unwrap().count(), 3);
+///
+/// // Test author filter
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "--author", "pratchett", "--no-attribution"]).unwrap();
+/// assert_eq!(matches.get_one::<String>("author").map(|s| s.as_str()), Some("pratchett"));
+/// assert!(matches.get_flag("no-attribution"));
+///
+/// // Test output format
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "--format", "ndjson"]).unwrap();
+/// assert_eq!(matches.get_one::<String>("format").map(|s| s.as_str()), Some("ndjson"));
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--format", "xml"]).is_err());
+///
+/// // Test seed
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "--seed", "42"]).unwrap();
+/// assert_eq!(matches.get_one::<u64>("seed"), Some(&42));
+///
+/// // Test fortune of the day
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "--date", "2024-05-04", "--salt", "ops"]).unwrap();
+/// assert_eq!(matches.get_one::<String>("date").map(|s| s.as_str()), Some("2024-05-04"));
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--salt", "ops"]).is_err());
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--daily", "--seed", "1"]).is_err());
+///
+/// // Test recursion depth
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "--max-depth", "2"]).unwrap();
+/// assert_eq!(matches.get_one::<usize>("max-depth"), Some(&2));
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--max-depth", "0"]).is_err());
+///
+/// // Test index subcommand
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "index", "fortunes"]).unwrap();
+/// assert_eq!(matches.subcommand_name(), Some("index"));
+///
+/// // Test shuffle bag
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "--shuffle"]).unwrap();
+/// assert!(matches.get_flag("shuffle"));
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--shuffle", "-s"]).is_err());
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--shuffle", "-e"]).is_err());
+///
+/// // Test history
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "--no-repeat-window", "10"]).unwrap();
+/// assert_eq!(matches.get_one::<usize>("no-repeat-window"), Some(&10));
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--no-repeat-window", "10", "--daily"]).is_err());
+/// assert!(cmd.clone().try_get_matches_from(vec!["app", "--no-repeat-window", "10", "--seed", "1"]).is_err());
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "history", "clear"]).unwrap();
+/// assert_eq!(matches.subcommand_name(), Some("history"));
+///
+/// // Test rot13 subcommand
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "rot13", "off/cookie"]).unwrap();
+/// assert_eq!(matches.subcommand_name(), Some("rot13"));
+///
+/// // Test lint subcommand
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "lint", "--format", "json", "fortunes"]).unwrap();
+/// assert_eq!(matches.subcommand_name(), Some("lint"));
+///
+/// // Test fmt subcommand
+/// let matches = cmd.clone().try_get_matches_from(vec!["app", "fmt", "--check", "fortunes"]).unwrap();
+/// assert_eq!(matches.subcommand_name(), Some("fmt"));
+///
+/// // Test convert subcommand
+/// let matches = cmd.try_get_matches_from(vec!["app", "convert", "oldtunes"]).unwrap();
+/// assert_eq!(matches.subcommand_name(), Some("convert"));
 /// ```
 pub fn build_cli() -> Command {
     command!()
@@ -50,23 +117,257 @@ pub fn build_cli() -> Command {
                 .value_name("pattern")
                 .help("Finds fortunes matching regex query."),
         )
+        .arg(
+            Arg::new("ignore-case")
+                .short('i')
+                .long("ignore-case")
+                .help("Makes --find match regardless of case.")
+                .requires("find")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("word")
+                .short('w')
+                .long("word")
+                .help("Makes --find only match whole words.")
+                .requires("find")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("multiline")
+                .long("multiline")
+                .help("Lets --find patterns match across line breaks.")
+                .requires("find")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("author")
+                .long("author")
+                .value_name("pattern")
+                .help("Only shows fortunes whose author matches regex query, regardless of case."),
+        )
+        .arg(
+            Arg::new("no-attribution")
+                .long("no-attribution")
+                .help("Hides the \"-- author\" line of fortunes.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["plain", "json", "ndjson"])
+                .help("Prints fortunes as plain text (default), a JSON value, or one JSON object per line."),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .value_parser(["auto", "always", "never"])
+                .help("Dims attributions: auto (default) only when printing to a terminal, always, or never."),
+        )
+        .arg(
+            Arg::new("list")
+                .short('f')
+                .long("list")
+                .help("Lists the fortune files that would be searched, with their fortune counts and odds.")
+                .conflicts_with("find")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("length")
                 .short('n')
                 .long("length")
-                .help("Finds a fortune that is shorter than provided number."),
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Finds a fortune that is at most N characters long, counting characters rather than bytes. Replaces short from the config file, and 0 turns length limits off."),
         )
         .arg(
             Arg::new("short")
                 .short('s')
                 .long("short")
-                .help("Shows a short aphorism. Repeat for shorter (-ss).")
+                .help("Shows a short aphorism, of at most 150 characters. Repeat for shorter (-ss).")
                 .action(ArgAction::Count),
         )
+        .arg(
+            Arg::new("by-size")
+                .long("by-size")
+                .help("Weights fortune files by their size in bytes (default).")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("equal-files")
+                .short('e')
+                .long("equal-files")
+                .help("Gives every fortune file the same chance of being picked.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("equal-fortunes")
+                .long("equal-fortunes")
+                .help("Gives every fortune the same chance of being picked.")
+                .action(ArgAction::SetTrue),
+        )
+        .group(ArgGroup::new("strategy").args(["by-size", "equal-files", "equal-fortunes"]))
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+                .help("Seeds the random choices, so the same seed always picks the same fortune."),
+        )
+        .arg(
+            Arg::new("daily")
+                .long("daily")
+                .help("Shows the fortune of the day, the same for the whole day (in UTC).")
+                .conflicts_with("seed")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("date")
+                .long("date")
+                .value_name("YYYY-MM-DD")
+                .help("Shows the fortune of the day for the given date. Implies --daily.")
+                .conflicts_with("seed"),
+        )
+        .arg(
+            Arg::new("salt")
+                .long("salt")
+                .value_name("SALT")
+                .help("Mixes SALT into the fortune of the day, so different teams get different picks.")
+                .requires("day"),
+        )
+        .group(ArgGroup::new("day").args(["daily", "date"]).multiple(true))
+        .arg(
+            Arg::new("no-repeat-window")
+                .long("no-repeat-window")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Avoids the last N fortunes shown, remembered in $XDG_STATE_HOME/fortune-kind.")
+                .conflicts_with_all(["seed", "day"]),
+        )
+        .arg(
+            Arg::new("shuffle")
+                .long("shuffle")
+                .help("Shows every fortune once, in random order, before repeating any. Ignores short and strategy from the config file.")
+                .conflicts_with_all(["short", "length", "author", "strategy", "seed", "day", "no-repeat-window"])
+                .action(ArgAction::SetTrue),
+        )
+        .args(depth_args())
         .arg(
             Arg::new("path")
-                .value_name("PATH")
-                .help("Path to a specific fortune file or directory.")
+                .value_name("[N%] PATH")
+                .help("Paths to specific fortune files or directories, each optionally preceded by the percentage of fortunes to draw from it.")
+                .num_args(1..)
                 .index(1),
         )
+        .subcommand(
+            Command::new("index")
+                .about("Writes strfile-compatible .dat index files for faster selection.")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Fortune file or directory to index. Defaults to FORTUNE_DIR.")
+                        .num_args(1..),
+                )
+                .args(depth_args()),
+        )
+        .subcommand(
+            Command::new("rot13")
+                .about("Rotates a fortune file into FILE.rot13, or unrotates a rotated one, replacing it.")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .help("Fortune files to rotate or unrotate. The originals are replaced.")
+                        .required(true)
+                        .num_args(1..),
+                ),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Checks fortune files for mistakes, exiting with an error if any are found.")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Fortune file or directory to check. Defaults to FORTUNE_DIR.")
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .value_parser(["plain", "json", "ndjson"])
+                        .default_value("plain")
+                        .help("Prints diagnostics as file:line lines, a JSON array, or one JSON object per line."),
+                )
+                .arg(
+                    Arg::new("deny-warnings")
+                        .long("deny-warnings")
+                        .help("Treats warnings as errors.")
+                        .action(ArgAction::SetTrue),
+                )
+                .args(depth_args()),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("Rewrites fortune files in a canonical form, leaving ascii-art as it is.")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Fortune file or directory to format. Defaults to FORTUNE_DIR.")
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Lists unformatted files instead of rewriting them, exiting with an error if there are any.")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("diff")
+                        .long("diff")
+                        .help("Like --check, but shows the changes as a unified diff.")
+                        .action(ArgAction::SetTrue),
+                )
+                .args(depth_args()),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Re-encodes fortune files that are not UTF-8, such as Latin-1 ones, as UTF-8.")
+                .arg(
+                    Arg::new("path")
+                        .value_name("PATH")
+                        .help("Fortune file or directory to convert. Defaults to FORTUNE_DIR.")
+                        .num_args(1..),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Lists files that are not UTF-8 instead of converting them, exiting with an error if there are any.")
+                        .action(ArgAction::SetTrue),
+                )
+                .args(depth_args()),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Manages the history of recently shown fortunes, see --no-repeat-window.")
+                .subcommand_required(true)
+                .subcommand(Command::new("clear").about("Forgets every fortune shown so far.")),
+        )
+}
+
+/// Arguments controlling how deep to look into directories of fortune files.
+fn depth_args() -> [Arg; 2] {
+    [
+        Arg::new("recursive")
+            .short('r')
+            .long("recursive")
+            .help("Looks for fortune files in subdirectories too.")
+            .action(ArgAction::SetTrue),
+        Arg::new("max-depth")
+            .long("max-depth")
+            .value_name("N")
+            .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+            .help("Looks for fortune files at most N directories deep. Implies --recursive."),
+    ]
 }
@@ -0,0 +1,240 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for the places fortune files are read from.
+//!
+//! Fortune files normally live on disk, but with the `embedded-fortunes`
+//! feature the curated `fortunes` collection (and with `embedded-off` the
+//! `off` collection) is also compiled into the binary, so it works without any
+//! data files installed. Both are read through the [`Collection`] trait.
+//!
+//! Embedded files have paths under [`EMBEDDED_ROOT`], such as
+//! `<embedded>/fortunes/fedi`, so the rest of the crate can treat them like
+//! any other path: [`of`] picks the collection a path belongs to.
+use crate::file;
+use crate::{Error, Result};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The directory embedded collections appear in.
+pub const EMBEDDED_ROOT: &str = "<embedded>";
+
+/// A place fortune files can be listed and read from.
+pub trait Collection: Sync {
+    /// Lists the fortune files under `path`.
+    ///
+    /// If `path` is a file, it is returned as is. If it is a directory, its
+    /// fortune files are listed, descending into subdirectories up to
+    /// `max_depth` levels deep: `1` only looks at the directory itself, and
+    /// `usize::MAX` has no limit. Hidden entries and `.dat` index files are
+    /// skipped, and entries are listed in order of their names.
+    fn walk(&self, path: &Path, max_depth: usize) -> Result<Vec<PathBuf>>;
+
+    /// Reads the raw bytes of the fortune file at `path`.
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// Returns the size in bytes of the file at `path`, and when it was last
+    /// modified, if known.
+    fn metadata(&self, path: &Path) -> Result<(u64, Option<SystemTime>)>;
+
+    /// Returns `true` if `path` is a fortune file or directory of this collection.
+    fn exists(&self, path: &Path) -> bool;
+}
+
+/// Fortune files on disk.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Filesystem;
+
+impl Collection for Filesystem {
+    fn walk(&self, path: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+        file::walk_disk(path, max_depth)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        fs::read(path).map_err(|e| Error::from_io(path, e))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<(u64, Option<SystemTime>)> {
+        let metadata = fs::metadata(path).map_err(|e| Error::from_io(path, e))?;
+        Ok((metadata.len(), metadata.modified().ok()))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/// Fortune files compiled into the binary, keyed by their path under [`EMBEDDED_ROOT`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Embedded {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl Embedded {
+    /// Parses an uncompressed archive, as written by `build.rs`.
+    ///
+    /// The archive is a sequence of files, each a big-endian `u32` length and
+    /// the path of the file relative to [`EMBEDDED_ROOT`], followed by a `u32`
+    /// length and the contents of the file. Returns `None` if it is truncated.
+    pub fn parse(mut archive: &[u8]) -> Option<Self> {
+        fn chunk<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+            let len = u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+            let chunk = bytes.get(4..4 + len)?;
+            *bytes = &bytes[4 + len..];
+            Some(chunk)
+        }
+
+        let mut files = BTreeMap::new();
+        while !archive.is_empty() {
+            let name = String::from_utf8(chunk(&mut archive)?.to_vec()).ok()?;
+            let contents = chunk(&mut archive)?.to_vec();
+            files.insert(Path::new(EMBEDDED_ROOT).join(name), contents);
+        }
+        Some(Embedded { files })
+    }
+
+    /// Returns `true` if no files are embedded.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl Collection for Embedded {
+    fn walk(&self, path: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+        if self.files.contains_key(path) {
+            return Ok(vec![path.to_path_buf()]);
+        }
+        if !self.exists(path) {
+            return Err(Error::NotFound(path.to_path_buf()));
+        }
+
+        Ok(self
+            .files
+            .keys()
+            .filter(|file| {
+                file.strip_prefix(path)
+                    .is_ok_and(|rest| rest.components().count() <= max_depth)
+            })
+            .cloned()
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(path.to_path_buf()))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<(u64, Option<SystemTime>)> {
+        match self.files.get(path) {
+            Some(contents) => Ok((contents.len() as u64, None)),
+            None => Err(Error::NotFound(path.to_path_buf())),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.keys().any(|file| file.starts_with(path))
+    }
+}
+
+/// Returns the collections compiled into the binary, or `None` if there are none.
+pub fn embedded() -> Option<&'static Embedded> {
+    #[cfg(feature = "embedded-fortunes")]
+    {
+        use std::sync::OnceLock;
+
+        static ARCHIVE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/collections.deflate"));
+        static EMBEDDED: OnceLock<Embedded> = OnceLock::new();
+
+        let embedded = EMBEDDED.get_or_init(|| {
+            let archive = miniz_oxide::inflate::decompress_to_vec(ARCHIVE)
+                .expect("the embedded archive is written by build.rs");
+            Embedded::parse(&archive).expect("the embedded archive is written by build.rs")
+        });
+        Some(embedded)
+    }
+    #[cfg(not(feature = "embedded-fortunes"))]
+    {
+        None
+    }
+}
+
+/// Returns the collection `path` belongs to.
+///
+/// Paths under [`EMBEDDED_ROOT`] belong to the [`embedded`] collections if
+/// there are any, and every other path to the [`Filesystem`].
+pub fn of(path: &Path) -> &'static dyn Collection {
+    match embedded() {
+        Some(embedded) if path.starts_with(EMBEDDED_ROOT) => embedded,
+        _ => &Filesystem,
+    }
+}
+
+/// Returns `true` if `path` is a fortune file or directory, on disk or embedded.
+pub fn exists(path: &Path) -> bool {
+    of(path).exists(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (name, contents) in files {
+            for chunk in [name.as_bytes(), contents.as_bytes()] {
+                bytes.extend((chunk.len() as u32).to_be_bytes());
+                bytes.extend(chunk);
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_embedded_walk_and_read() {
+        let embedded = Embedded::parse(&archive(&[
+            ("fortunes/fedi", "Be kind.\n%\n"),
+            ("fortunes/tao", "Flow.\n%\n"),
+            ("off/unkind", "Not here.\n%\n"),
+        ]))
+        .unwrap();
+        let root = Path::new(EMBEDDED_ROOT);
+
+        let files = embedded.walk(&root.join("fortunes"), 1).unwrap();
+        assert_eq!(
+            files,
+            [root.join("fortunes/fedi"), root.join("fortunes/tao")]
+        );
+        assert_eq!(embedded.walk(root, 1).unwrap().len(), 0);
+        assert_eq!(embedded.walk(root, 2).unwrap().len(), 3);
+        assert_eq!(
+            embedded.read(&root.join("fortunes/fedi")).unwrap(),
+            b"Be kind.\n%\n"
+        );
+        assert_eq!(
+            embedded.metadata(&root.join("off/unkind")).unwrap(),
+            (12, None)
+        );
+
+        assert!(embedded.exists(&root.join("off")));
+        assert!(!embedded.exists(&root.join("oldtunes")));
+        assert!(matches!(
+            embedded.walk(&root.join("oldtunes"), 1),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_embedded_parse_rejects_truncated() {
+        let bytes = archive(&[("fortunes/fedi", "Be kind.\n%\n")]);
+
+        assert_eq!(Embedded::parse(&bytes[..bytes.len() - 1]), None);
+        assert!(Embedded::parse(&[]).unwrap().is_empty());
+    }
+}
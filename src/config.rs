@@ -0,0 +1,274 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for the optional configuration file.
+//!
+//! The file lives at `$XDG_CONFIG_HOME/fortune-kind/config.toml` (see
+//! [`default_path`]) and every setting in it is optional:
+//!
+//! ```toml
+//! # Where to look for fortunes when no PATH is given, like FORTUNE_PATH
+//! paths = ["~/fortunes", "/usr/share/fortune-kind/fortunes"]
+//! # Where to look for unkind fortunes, for --unkind and --all
+//! unkind-paths = ["/usr/share/fortune-kind/off"]
+//! # Like passing -s this many times, ignored with -n or --shuffle
+//! short = 1
+//! # by-size, equal-files or equal-fortunes, ignored with --shuffle
+//! strategy = "equal-fortunes"
+//! # Files to skip when reading a directory, by name
+//! exclude = ["ascii-art"]
+//! # plain, json or ndjson
+//! format = "plain"
+//! # auto, always or never
+//! color = "auto"
+//! # Collections `fortune-kind fmt` leaves as they are
+//! preformatted = ["ascii-art"]
+//! ```
+//!
+//! Settings are merged with environment variables and command line flags, in
+//! this order of precedence:
+//!
+//! 1. Command line flags and arguments
+//! 2. Environment variables (`FORTUNE_PATH`, `FORTUNE_DIR`, `FORTUNE_OFF_DIR`, `NO_COLOR`)
+//! 3. The configuration file
+//! 4. Built-in defaults
+use crate::collection;
+use crate::random::Strategy;
+use crate::{Error, Result};
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Settings read from the configuration file. Unset settings are `None` or empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Fortune files or directories to pick from when no path is given.
+    ///
+    /// Like `FORTUNE_PATH`, a file in an earlier directory shadows files at the
+    /// same path in later ones.
+    pub paths: Option<Vec<PathBuf>>,
+    /// Fortune files or directories of unkind fortunes.
+    pub unkind_paths: Option<Vec<PathBuf>>,
+    /// How short fortunes should be, as for [`QuoteOptions::short`](crate::fortune::QuoteOptions::short).
+    ///
+    /// Ignored with `-n`, which sets the length explicitly, and with
+    /// `--shuffle`, which shows every fortune.
+    pub short: Option<u8>,
+    /// How fortune files are weighted against each other.
+    ///
+    /// Ignored with `--shuffle`, which shows every fortune.
+    pub strategy: Option<Strategy>,
+    /// Names of fortune files to skip when reading a directory.
+    pub exclude: Vec<String>,
+    /// How fortunes are printed.
+    pub format: Option<Format>,
+    /// Whether attributions are coloured.
+    pub color: Option<Color>,
+    /// Names of collections `fmt` leaves untouched, by default
+    /// [`PREFORMATTED`](crate::formatter::PREFORMATTED).
+    pub preformatted: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Loads the configuration file at `path`.
+    ///
+    /// A missing file is an empty configuration. A leading `~/` in paths is
+    /// expanded to the home directory.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::BadConfig`] if the file is not valid TOML, or has unknown or
+    ///   malformed settings.
+    /// * An I/O variant of [`Error`] if the file exists but cannot be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fortune_kind::config::{Config, Format};
+    /// use fortune_kind::random::Strategy;
+    /// use std::fs;
+    /// use tempfile::tempdir;
+    ///
+    /// let dir = tempdir().unwrap();
+    /// let path = dir.path().join("config.toml");
+    /// fs::write(&path, "strategy = \"equal-fortunes\"\nformat = \"json\"\n").unwrap();
+    ///
+    /// let config = Config::load(&path).unwrap();
+    /// assert_eq!(config.strategy, Some(Strategy::EqualFortunes));
+    /// assert_eq!(config.format, Some(Format::Json));
+    /// assert_eq!(config.short, None);
+    /// ```
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(Error::from_io(path, e)),
+        };
+
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|e| Error::BadConfig(path.to_path_buf(), e.message().to_string()))?;
+        for paths in [&mut config.paths, &mut config.unkind_paths]
+            .into_iter()
+            .flatten()
+        {
+            paths.iter_mut().for_each(|p| *p = expand_home(p));
+        }
+        Ok(config)
+    }
+}
+
+/// Returns the default location of the configuration file.
+///
+/// This is `$XDG_CONFIG_HOME/fortune-kind/config.toml`, falling back to
+/// `$HOME/.config/fortune-kind/config.toml`. Returns `None` if neither
+/// variable is set.
+pub fn default_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("fortune-kind").join("config.toml"))
+}
+
+/// The data directory chosen when fortune-kind was built, if any.
+///
+/// Packagers set it like `./configure --datadir`, by building with
+/// `FORTUNE_KIND_DATADIR=/usr/share/fortune-kind cargo build --release`. It
+/// should contain the `fortunes` and `off` collections.
+pub const BUILD_DATADIR: Option<&str> = option_env!("FORTUNE_KIND_DATADIR");
+
+/// Returns the directories installed collections are looked for in, most important first.
+///
+/// These are, following the XDG base directory specification:
+///
+/// 1. `$XDG_DATA_HOME/fortune-kind`, falling back to `$HOME/.local/share/fortune-kind`
+/// 2. `fortune-kind` in each of `$XDG_DATA_DIRS`, falling back to
+///    `/usr/local/share` and `/usr/share`
+/// 3. [`BUILD_DATADIR`], if set
+/// 4. [`EMBEDDED_ROOT`](collection::EMBEDDED_ROOT), if collections are
+///    [embedded](collection::embedded) in the binary
+/// 5. The source tree fortune-kind was built from, for `cargo run`
+///
+/// Each directory holds a `fortunes` and an `off` collection. Directories are
+/// listed whether or not they exist.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut bases = vec![];
+    match env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => bases.push(PathBuf::from(dir)),
+        None => bases.extend(env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share"))),
+    }
+    match env::var_os("XDG_DATA_DIRS").filter(|v| !v.is_empty()) {
+        Some(dirs) => bases.extend(env::split_paths(&dirs).filter(|d| d.is_absolute())),
+        None => bases.extend(["/usr/local/share", "/usr/share"].map(PathBuf::from)),
+    }
+
+    let mut dirs: Vec<PathBuf> = bases.into_iter().map(|b| b.join("fortune-kind")).collect();
+    dirs.extend(BUILD_DATADIR.map(PathBuf::from));
+    if collection::embedded().is_some() {
+        dirs.push(PathBuf::from(collection::EMBEDDED_ROOT));
+    }
+    dirs.push(PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+
+    let mut seen = HashSet::new();
+    dirs.retain(|d| seen.insert(d.clone()));
+    dirs
+}
+
+/// Replaces a leading `~` in `path` with the home directory, if it is known.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// How fortunes are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// The text of each fortune, with `%` between search results.
+    #[default]
+    Plain,
+    /// A JSON object, or an array of them for search results.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(format!("unknown format {:?}", s)),
+        }
+    }
+}
+
+/// Whether output is coloured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    /// Colour output if it goes to a terminal.
+    #[default]
+    Auto,
+    /// Always colour output.
+    Always,
+    /// Never colour output.
+    Never,
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!("unknown color setting {:?}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_missing_is_default() {
+        let dir = tempdir().unwrap();
+
+        assert_eq!(
+            Config::load(&dir.path().join("config.toml")).unwrap(),
+            Config::default()
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_bad_settings() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let bad = |contents: &str| {
+            fs::write(&path, contents).unwrap();
+            matches!(Config::load(&path), Err(Error::BadConfig(..)))
+        };
+
+        assert!(bad("strategy = \"loudest\""));
+        assert!(bad("shortness = 1"));
+        assert!(bad("short = -1"));
+        assert!(bad("paths = "));
+        assert!(!bad("exclude = [\"ascii-art\"]\ncolor = \"never\""));
+    }
+}
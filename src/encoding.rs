@@ -0,0 +1,231 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for reading fortune files that are not UTF-8.
+//!
+//! Many collections, such as those from `fortune-mod`, predate UTF-8 and are
+//! encoded as Latin-1 or Windows-1252. Rather than failing on them, [`decode`]
+//! reads every byte that is not part of valid UTF-8 as Windows-1252, which
+//! covers every printable Latin-1 character. Files mixing the two, such as a
+//! UTF-8 file with a few Latin-1 lines pasted in, are read the same way, so no
+//! byte is ever lost and re-encoding a file as UTF-8 is safe.
+//!
+//! The [`Fallback`] used for a file is handed back to the caller, such as in
+//! [`Fortune::fallback`](crate::fortune::Fortune::fallback), so it can warn
+//! about the file, and [`check_all`] finds the files to re-encode.
+use crate::collection;
+use crate::file;
+use crate::{Error, Result};
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The characters Windows-1252 puts at bytes `0x80` to `0x9F`.
+///
+/// The five bytes it leaves undefined map to the C1 control characters, as
+/// in Latin-1, so every byte decodes to something.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+/// An encoding a fortune file can be read as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Valid UTF-8.
+    Utf8,
+    /// UTF-8 mixed with bytes that are not, which are read as Windows-1252.
+    Mixed,
+    /// Windows-1252, a superset of the printable range of Latin-1.
+    Windows1252,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "UTF-8"),
+            Encoding::Mixed => write!(f, "UTF-8 mixed with Windows-1252"),
+            Encoding::Windows1252 => write!(f, "Windows-1252"),
+        }
+    }
+}
+
+/// How a file that is not valid UTF-8 was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fallback {
+    /// The encoding the file was read as.
+    pub encoding: Encoding,
+    /// The line of the first byte that is not valid UTF-8, starting at 1.
+    pub line: usize,
+}
+
+/// Decodes the contents of a fortune file.
+///
+/// Valid UTF-8 is returned as is. Otherwise each byte that is not part of a
+/// valid UTF-8 sequence is read as Windows-1252, and the encoding is returned
+/// along with the first line that is not UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::encoding::{self, Encoding};
+///
+/// let (text, fallback) = encoding::decode(b"Caf\xe9 \x93au lait\x94\n%\n".to_vec());
+/// assert_eq!(text, "Café “au lait”\n%\n");
+/// assert_eq!(fallback.unwrap().encoding, Encoding::Windows1252);
+///
+/// let (text, fallback) = encoding::decode("Café\n%\n".into());
+/// assert_eq!((text.as_str(), fallback), ("Café\n%\n", None));
+/// ```
+pub fn decode(bytes: Vec<u8>) -> (String, Option<Fallback>) {
+    let err = match String::from_utf8(bytes) {
+        Ok(text) => return (text, None),
+        Err(err) => err,
+    };
+    let valid = err.utf8_error().valid_up_to();
+    let bytes = err.into_bytes();
+    let line = bytes[..valid].iter().filter(|&&b| b == b'\n').count() + 1;
+
+    let mut text = String::with_capacity(bytes.len());
+    let mut multibyte = false;
+    let mut rest = &bytes[..];
+    loop {
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(valid) => (valid, 0),
+            Err(e) => {
+                let valid = std::str::from_utf8(&rest[..e.valid_up_to()])
+                    .expect("bytes up to valid_up_to are UTF-8");
+                (valid, e.error_len().unwrap_or(rest.len() - valid.len()))
+            }
+        };
+        multibyte |= !valid.is_ascii();
+        text.push_str(valid);
+        rest = &rest[valid.len()..];
+        if invalid == 0 {
+            break;
+        }
+        text.extend(rest[..invalid].iter().map(|&b| windows_1252(b)));
+        rest = &rest[invalid..];
+    }
+
+    let encoding = match multibyte {
+        true => Encoding::Mixed,
+        false => Encoding::Windows1252,
+    };
+    (text, Some(Fallback { encoding, line }))
+}
+
+/// Decodes a single Windows-1252 byte.
+fn windows_1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => WINDOWS_1252[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+/// A fortune file to re-encode as UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// The fortune file.
+    pub path: PathBuf,
+    /// How the file is read now.
+    pub fallback: Fallback,
+    /// The contents of the file, decoded.
+    pub text: String,
+}
+
+impl Conversion {
+    /// Writes the decoded contents back to the file as UTF-8.
+    ///
+    /// ROT13 files are written as they are, since rotating leaves non-ASCII
+    /// characters alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] naming the file if it cannot be written.
+    pub fn write(&self) -> Result<()> {
+        fs::write(&self.path, &self.text).map_err(|e| Error::from_io(&self.path, e))
+    }
+}
+
+/// Finds the fortune files under `path` that are not UTF-8, descending
+/// `max_depth` levels of directories as for
+/// [`Collection::walk`](collection::Collection::walk).
+///
+/// Embedded collections are skipped, since they cannot be written.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if `path` or a file under it cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::encoding;
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let dir = tempdir().unwrap();
+/// fs::write(dir.path().join("oldtunes"), b"Caf\xe9\n%\n").unwrap();
+/// fs::write(dir.path().join("newtunes"), "Café\n%\n").unwrap();
+///
+/// let conversions = encoding::check_all(dir.path(), 1).unwrap();
+/// assert_eq!(conversions.len(), 1);
+/// conversions[0].write().unwrap();
+/// assert_eq!(fs::read_to_string(dir.path().join("oldtunes")).unwrap(), "Café\n%\n");
+/// ```
+pub fn check_all(path: &Path, max_depth: usize) -> Result<Vec<Conversion>> {
+    let mut conversions = vec![];
+    for source in file::walk(path, max_depth)? {
+        if source.starts_with(collection::EMBEDDED_ROOT) {
+            continue;
+        }
+        let bytes = fs::read(&source).map_err(|e| Error::from_io(&source, e))?;
+        if let (text, Some(fallback)) = decode(bytes) {
+            conversions.push(Conversion {
+                path: source,
+                fallback,
+                text,
+            });
+        }
+    }
+    Ok(conversions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_windows_1252() {
+        let (text, fallback) = decode(b"One\n%\n\x80 \x85 na\xefve \x81\n%\n".to_vec());
+
+        assert_eq!(text, "One\n%\n€ … naïve \u{81}\n%\n");
+        assert_eq!(
+            fallback,
+            Some(Fallback {
+                encoding: Encoding::Windows1252,
+                line: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_mixed_keeps_every_byte() {
+        let (text, fallback) = decode(b"Caf\xc3\xa9\n%\nna\xefve \xe2\x80\n%\n".to_vec());
+
+        assert_eq!(text, "Café\n%\nnaïve â€\n%\n");
+        assert_eq!(
+            fallback,
+            Some(Fallback {
+                encoding: Encoding::Mixed,
+                line: 3
+            })
+        );
+    }
+}
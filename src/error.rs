@@ -0,0 +1,192 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! The error type shared by every module of the library.
+//!
+//! Each variant maps to a distinct process exit code (see [`Error::exit_code`]),
+//! so scripts can tell "nothing matched" apart from "the install is broken".
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A specialized `Result` type for fortune-kind operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error encountered while reading or selecting fortunes.
+#[derive(Debug)]
+pub enum Error {
+    /// A fortune file or directory does not exist.
+    NotFound(PathBuf),
+    /// A fortune file or directory could not be read due to its permissions.
+    PermissionDenied(PathBuf),
+    /// A fortune file is not valid UTF-8.
+    InvalidUtf8(PathBuf),
+    /// A file would be written over one that already exists.
+    AlreadyExists(PathBuf),
+    /// Any other I/O error while accessing a fortune file or directory.
+    Io(PathBuf, io::Error),
+    /// The given paths contain no fortunes at all.
+    EmptyCollection(Vec<PathBuf>),
+    /// Fortunes exist, but none satisfy the given criteria, e.g. `is at most 5 characters long`.
+    NoMatch(Vec<PathBuf>, String),
+    /// A search pattern could not be compiled.
+    BadPattern(String),
+    /// Percentages given for fortune sources are malformed or add up to more than 100.
+    BadWeights(String),
+    /// A date is not a valid calendar date of the form `YYYY-MM-DD`.
+    BadDate(String),
+    /// The configuration file at the path is malformed, for the given reason.
+    BadConfig(PathBuf, String),
+    /// Checking fortune files found this many errors, see [`lint`](crate::lint).
+    LintFailed(usize),
+    /// This many fortune files are not formatted, see [`formatter`](crate::formatter).
+    Unformatted(usize),
+    /// This many fortune files are not UTF-8, see [`encoding`](crate::encoding).
+    Unconverted(usize),
+}
+
+impl Error {
+    /// Classifies an I/O error encountered while accessing `path`.
+    pub fn from_io(path: &Path, e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(path.to_path_buf()),
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(path.to_path_buf()),
+            io::ErrorKind::AlreadyExists => Error::AlreadyExists(path.to_path_buf()),
+            // `read_to_string` reports malformed UTF-8 as invalid data.
+            io::ErrorKind::InvalidData => Error::InvalidUtf8(path.to_path_buf()),
+            _ => Error::Io(path.to_path_buf(), e),
+        }
+    }
+
+    /// Returns the process exit code for this error.
+    ///
+    /// | Code | Meaning                                               |
+    /// |------|-------------------------------------------------------|
+    /// | 1    | No fortune matched ([`Error::NoMatch`])               |
+    /// | 2    | Invalid search pattern, percentages or date           |
+    /// | 65   | Empty, malformed, unformatted or non-UTF-8 collection |
+    /// | 66   | Path not found                                        |
+    /// | 73   | A file to write already exists                        |
+    /// | 74   | Other I/O error                                       |
+    /// | 77   | Permission denied                                     |
+    /// | 78   | Malformed configuration file                          |
+    ///
+    /// Codes from 65 upwards follow BSD's `sysexits.h`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::NoMatch(..) => 1,
+            Error::BadPattern(_) | Error::BadWeights(_) | Error::BadDate(_) => 2,
+            Error::EmptyCollection(_)
+            | Error::InvalidUtf8(_)
+            | Error::LintFailed(_)
+            | Error::Unformatted(_)
+            | Error::Unconverted(_) => 65,
+            Error::NotFound(_) => 66,
+            Error::AlreadyExists(_) => 73,
+            Error::Io(..) => 74,
+            Error::PermissionDenied(_) => 77,
+            Error::BadConfig(..) => 78,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(path) => write!(f, "The fortune path {:?} was not found.", path),
+            Error::PermissionDenied(path) => write!(f, "Permission denied reading {:?}.", path),
+            Error::InvalidUtf8(path) => write!(
+                f,
+                "The fortune file {:?} is not valid UTF-8, run `fortune-kind convert` to re-encode it.",
+                path
+            ),
+            Error::AlreadyExists(path) => {
+                write!(f, "Refusing to overwrite the existing file {:?}.", path)
+            }
+            Error::Io(path, e) => write!(f, "Could not read fortunes from {:?}: {}", path, e),
+            Error::EmptyCollection(paths) => write!(f, "No fortunes found in {:?}.", paths),
+            Error::NoMatch(paths, criteria) => write!(f, "No fortune in {:?} {}.", paths, criteria),
+            Error::BadPattern(e) => write!(f, "Invalid search pattern: {}", e),
+            Error::BadWeights(e) => write!(f, "Invalid percentages: {}", e),
+            Error::BadDate(date) => {
+                write!(f, "Invalid date {:?}, expected YYYY-MM-DD.", date)
+            }
+            Error::BadConfig(path, e) => write!(f, "Invalid configuration in {:?}: {}", path, e),
+            Error::LintFailed(errors) => {
+                write!(f, "Found {} error(s) in the fortune files.", errors)
+            }
+            Error::Unformatted(files) => write!(
+                f,
+                "{} fortune file(s) are not formatted, run `fortune-kind fmt` to fix them.",
+                files
+            ),
+            Error::Unconverted(files) => write!(
+                f,
+                "{} fortune file(s) are not UTF-8, run `fortune-kind convert` to re-encode them.",
+                files
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_io_classifies_kinds() {
+        let path = Path::new("fortunes");
+        let err = |kind| Error::from_io(path, io::Error::from(kind));
+
+        assert!(matches!(err(io::ErrorKind::NotFound), Error::NotFound(_)));
+        assert!(matches!(
+            err(io::ErrorKind::PermissionDenied),
+            Error::PermissionDenied(_)
+        ));
+        assert!(matches!(
+            err(io::ErrorKind::InvalidData),
+            Error::InvalidUtf8(_)
+        ));
+        assert!(matches!(
+            err(io::ErrorKind::AlreadyExists),
+            Error::AlreadyExists(_)
+        ));
+        assert!(matches!(err(io::ErrorKind::Interrupted), Error::Io(..)));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        // Every variant, with the code documented for it
+        let expected = [
+            (Error::NoMatch(vec![], String::new()), 1),
+            (Error::BadPattern(String::new()), 2),
+            (Error::BadWeights(String::new()), 2),
+            (Error::BadDate(String::new()), 2),
+            (Error::EmptyCollection(vec![]), 65),
+            (Error::InvalidUtf8(PathBuf::new()), 65),
+            (Error::LintFailed(1), 65),
+            (Error::Unformatted(1), 65),
+            (Error::Unconverted(1), 65),
+            (Error::NotFound(PathBuf::new()), 66),
+            (Error::AlreadyExists(PathBuf::new()), 73),
+            (Error::Io(PathBuf::new(), io::Error::other("")), 74),
+            (Error::PermissionDenied(PathBuf::new()), 77),
+            (Error::BadConfig(PathBuf::new(), String::new()), 78),
+        ];
+
+        for (error, code) in &expected {
+            assert_eq!(error.exit_code(), *code, "{:?}", error);
+        }
+    }
+}
@@ -5,48 +5,149 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 
 //! A module for file related actions.
+use crate::collection;
+use crate::encoding::{self, Fallback};
+use crate::rot13;
+use crate::{Error, Result};
+
+use std::collections::HashSet;
 use std::fs;
-use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The default depth for [`walk`]: only files directly inside a directory.
+pub const SHALLOW: usize = 1;
 
-/// Reads the contents of a path and returns them as a vector of strings.
+/// Lists the fortune files under a path.
+///
+/// If `path` is a file, it is returned as is. If it is a directory, its fortune
+/// files are listed, descending into subdirectories up to `max_depth` levels
+/// deep: `1` only looks at the directory itself, and `usize::MAX` has no limit.
+///
+/// Hidden entries and `.dat` index files are skipped, and each directory is
+/// visited at most once, so symlink loops cannot cause endless recursion.
+/// Entries are listed in order of their names, so the result is the same on
+/// every run.
+///
+/// Embedded collections are listed the same way, see [`collection`].
+///
+/// # Errors
+///
+/// Returns [`Error::NotFound`] or [`Error::PermissionDenied`] if the path or a
+/// directory below it is invalid or inaccessible.
+pub fn walk(path: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+    collection::of(path).walk(path, max_depth)
+}
+
+/// Lists the fortune files under a path on disk, see [`walk`].
+pub(crate) fn walk_disk(path: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else {
+        walk_dir(path, max_depth, &mut HashSet::new(), &mut files)?;
+    }
+
+    Ok(files)
+}
+
+/// Recursive helper for [`walk_disk`], tracking visited directories in `seen`.
+fn walk_dir(
+    dir: &Path,
+    depth: usize,
+    seen: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let canonical = fs::canonicalize(dir).map_err(|e| Error::from_io(dir, e))?;
+    if depth == 0 || !seen.insert(canonical) {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|e| e.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<PathBuf>>>()
+        })
+        .map_err(|e| Error::from_io(dir, e))?;
+    // `read_dir` makes no promises about order, but seeded picks rely on one.
+    entries.sort();
+
+    for path in entries {
+        if is_hidden(&path) {
+            continue;
+        }
+        if path.is_dir() {
+            walk_dir(&path, depth - 1, seen, files)?;
+        } else if is_fortune_file(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the contents of a path and returns them alongside the file they came from.
 ///
 /// If `path` is a file, the vector will contain a single entry with that file's contents.
-/// If `path` is a directory, it will contain the contents of all files within that directory.
+/// If `path` is a directory, it will contain the contents of all files found by [`walk`].
 ///
 /// # Arguments
 ///
 /// * `path` - The file or directory path to read.
+/// * `max_depth` - How many levels of directories to descend, see [`walk`].
 ///
 /// # Returns
 ///
-/// A `Result` containing a vector of strings, where each string represents the contents of a file in the directory.
+/// A `Result` containing a vector of `(path, contents, fallback)` triples, one
+/// for each file read, see [`read_file_decoded`].
 ///
 /// # Errors
 ///
-/// Returns an error if the path is invalid, inaccessible, or if reading fails.
-pub fn read_all_files<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let path = path.as_ref();
-    let mut contents_vec = Vec::new();
+/// Returns [`Error::NotFound`] or [`Error::PermissionDenied`] if the path is
+/// invalid or inaccessible.
+pub fn read_all_files<P: AsRef<Path>>(
+    path: P,
+    max_depth: usize,
+) -> Result<Vec<(PathBuf, String, Option<Fallback>)>> {
+    walk(path.as_ref(), max_depth)?
+        .into_iter()
+        .map(|path| read_file_decoded(&path).map(|(contents, fallback)| (path, contents, fallback)))
+        .collect()
+}
 
-    if path.is_file() {
-        let mut contents = String::new();
-        fs::File::open(path)?.read_to_string(&mut contents)?;
-        contents_vec.push(contents);
-        return Ok(contents_vec);
-    }
+/// Reads a single fortune file into a string.
+///
+/// Files that are not UTF-8 are decoded as described in [`encoding::decode`],
+/// and files holding ROT13 encoded fortunes (see [`rot13::is_rotated`]) are
+/// decoded too.
+///
+/// # Errors
+///
+/// Returns an [`Error`] naming `path` if the file cannot be read.
+pub fn read_file(path: &Path) -> Result<String> {
+    read_file_decoded(path).map(|(contents, _)| contents)
+}
 
-    let entries = fs::read_dir(path)?;
-    for entry in entries {
-        let path = entry?.path();
-        if path.is_file() {
-            let mut contents = String::new();
-            fs::File::open(path)?.read_to_string(&mut contents)?;
-            contents_vec.push(contents);
-        }
+/// Like [`read_file`], but also returns how the file was decoded if it is not UTF-8.
+pub fn read_file_decoded(path: &Path) -> Result<(String, Option<Fallback>)> {
+    let (contents, fallback) = encoding::decode(collection::of(path).read(path)?);
+    if rot13::is_rotated(path) {
+        return Ok((rot13::rotate(&contents), fallback));
     }
+    Ok((contents, fallback))
+}
+
+/// Returns `true` if `path` is a fortune file, rather than a directory or a `.dat` index.
+pub fn is_fortune_file(path: &Path) -> bool {
+    path.is_file() && path.extension().map_or(true, |ext| ext != "dat")
+}
 
-    Ok(contents_vec)
+/// Returns `true` if the file name of `path` starts with a dot.
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
 }
 
 /// Retrieves the sizes of files in the specified directory.
@@ -57,12 +158,13 @@ pub fn read_all_files<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Box<dyn st
 /// # Arguments
 ///
 /// * `path` - A generic parameter that implements `AsRef<Path>`, which is the path to the directory to read.
+/// * `max_depth` - How many levels of directories to descend, see [`walk`].
 ///
 /// # Returns
 ///
-/// A `std::io::Result` containing a vector of tuples. Each tuple consists of a `u64` file size
+/// A `Result` containing a vector of tuples. Each tuple consists of a `u64` file size
 /// and a `PathBuf` corresponding to a file's path. If an error occurs during directory traversal
-/// or metadata retrieval, an `io::Error` is returned.
+/// or metadata retrieval, an [`Error`] naming the offending path is returned.
 ///
 /// # Errors
 ///
@@ -70,32 +172,37 @@ pub fn read_all_files<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Box<dyn st
 ///
 /// * The path does not exist.
 /// * The current process lacks permissions to read the directory.
-/// * The path points to a non-directory file.
 /// * Any I/O error encountered when reading the directory contents or retrieving file metadata.
-///
-/// ```
-pub fn get_file_sizes<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<(u64, PathBuf)>> {
-    let path = path.as_ref();
-
-    if path.is_file() {
-        let metadata = fs::metadata(path)?;
-        return Ok(vec![(metadata.len(), path.to_path_buf())]);
-    }
-
-    let entries = fs::read_dir(path)?;
-    let mut files: Vec<(u64, PathBuf)> = vec![];
-
-    for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_file() {
-            let metadata = fs::metadata(&path)?;
-            files.push((metadata.len(), path));
-        }
-    }
+pub fn get_file_sizes<P: AsRef<Path>>(path: P, max_depth: usize) -> Result<Vec<(u64, PathBuf)>> {
+    walk(path.as_ref(), max_depth)?
+        .into_iter()
+        .map(|path| {
+            let (size, _) = collection::of(&path).metadata(&path)?;
+            Ok((size, path))
+        })
+        .collect()
+}
 
-    Ok(files)
+/// Retrieves the size and modification time of each fortune file under `path`.
+///
+/// Like [`get_file_sizes`], but also returns when each file was last modified,
+/// so callers can tell whether a collection changed since they last saw it.
+/// The modification time is `None` on platforms that do not record it.
+///
+/// # Errors
+///
+/// Same as [`get_file_sizes`].
+pub fn get_file_stamps<P: AsRef<Path>>(
+    path: P,
+    max_depth: usize,
+) -> Result<Vec<(u64, Option<SystemTime>, PathBuf)>> {
+    walk(path.as_ref(), max_depth)?
+        .into_iter()
+        .map(|path| {
+            let (size, modified) = collection::of(&path).metadata(&path)?;
+            Ok((size, modified, path))
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -123,19 +230,82 @@ mod tests {
     #[test]
     fn test_read_all_files() {
         let tmp_dir = setup_test_directory();
-        let result = read_all_files(tmp_dir.path().to_str().unwrap());
+        let result = read_all_files(tmp_dir.path().to_str().unwrap(), SHALLOW);
 
         assert!(result.is_ok());
-        let contents = result.unwrap();
+        let contents: Vec<String> = result.unwrap().into_iter().map(|(_, c, _)| c).collect();
         assert_eq!(contents.len(), 2);
         assert!(contents.contains(&"Content of file1\n".to_string()));
         assert!(contents.contains(&"Content of file2\n".to_string()));
     }
 
+    /// test_read_all_files_skips_indexes: Tests that `.dat` index files are not read as fortunes.
+    #[test]
+    fn test_read_all_files_skips_indexes() {
+        let tmp_dir = setup_test_directory();
+        File::create(tmp_dir.path().join("file1.txt.dat")).unwrap();
+
+        assert_eq!(read_all_files(tmp_dir.path(), SHALLOW).unwrap().len(), 2);
+        assert_eq!(get_file_sizes(tmp_dir.path(), SHALLOW).unwrap().len(), 2);
+    }
+
     /// test_read_all_files_invalid_dir: Tests the error handling of read_all_files when given an invalid directory.
     #[test]
     fn test_read_all_files_invalid_dir() {
-        let result = read_all_files("invalid_directory");
-        assert!(result.is_err());
+        let result = read_all_files("invalid_directory", SHALLOW);
+        assert!(matches!(result, Err(Error::NotFound(_))));
+    }
+
+    /// test_read_all_files_latin1: Tests that a non-UTF-8 file is read as Windows-1252.
+    #[test]
+    fn test_read_all_files_latin1() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("latin1");
+        File::create(&path).unwrap().write_all(b"caf\xe9").unwrap();
+
+        let files = read_all_files(tmp_dir.path(), SHALLOW).unwrap();
+        assert_eq!(files[0].0, path);
+        assert_eq!(files[0].1, "café");
+        assert_eq!(
+            files[0].2.unwrap().encoding,
+            encoding::Encoding::Windows1252
+        );
+    }
+
+    /// test_walk_depth: Tests that nested directories are only read up to the depth limit.
+    #[test]
+    fn test_walk_depth() {
+        let tmp_dir = setup_test_directory();
+        let nested = tmp_dir.path().join("off").join("unrotated");
+        fs::create_dir_all(&nested).unwrap();
+        File::create(tmp_dir.path().join("off").join("middle")).unwrap();
+        File::create(nested.join("deep")).unwrap();
+
+        assert_eq!(walk(tmp_dir.path(), SHALLOW).unwrap().len(), 2);
+        assert_eq!(walk(tmp_dir.path(), 2).unwrap().len(), 3);
+        assert_eq!(walk(tmp_dir.path(), usize::MAX).unwrap().len(), 4);
+    }
+
+    /// test_walk_skips_hidden: Tests that hidden files and directories are ignored.
+    #[test]
+    fn test_walk_skips_hidden() {
+        let tmp_dir = setup_test_directory();
+        File::create(tmp_dir.path().join(".hidden")).unwrap();
+        fs::create_dir(tmp_dir.path().join(".git")).unwrap();
+        File::create(tmp_dir.path().join(".git").join("HEAD")).unwrap();
+
+        assert_eq!(walk(tmp_dir.path(), usize::MAX).unwrap().len(), 2);
+    }
+
+    /// test_walk_symlink_loop: Tests that a symlink back to a parent doesn't recurse forever.
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_symlink_loop() {
+        let tmp_dir = setup_test_directory();
+        let sub = tmp_dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        std::os::unix::fs::symlink(tmp_dir.path(), sub.join("loop")).unwrap();
+
+        assert_eq!(walk(tmp_dir.path(), usize::MAX).unwrap().len(), 2);
     }
 }
@@ -0,0 +1,457 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for rewriting fortune files into a canonical form.
+//!
+//! A formatted file has LF line endings, no trailing whitespace, no blank
+//! lines around fortunes, a bare `%` line after every fortune (including the
+//! last) and attributions written as `\t\t-- Author`, the style most of the
+//! collection already uses. Attributions tacked onto the end of a fortune,
+//! like `never wrong. -Samuel Goldwyn`, are moved onto a line of their own.
+//!
+//! The text of fortunes is otherwise left alone, so indentation and
+//! preformatted fortunes survive. Whole collections of ASCII art, where even
+//! trailing whitespace matters, can be skipped by name, see [`PREFORMATTED`].
+use crate::collection;
+use crate::file;
+use crate::fortune::Parts;
+use crate::parser;
+use crate::rot13;
+use crate::{Error, Result};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Names of collections left untouched by default, as their layout is the content.
+pub const PREFORMATTED: &[&str] = &["ascii-art"];
+
+/// The number of unchanged lines shown around each change in a diff.
+const CONTEXT: usize = 3;
+
+/// The canonical indentation of an attribution line.
+const ATTRIBUTION_INDENT: &str = "\t\t";
+
+/// A fortune file whose formatting would change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The fortune file.
+    pub path: PathBuf,
+    /// The current contents, decoded if the file is ROT13 encoded.
+    pub before: String,
+    /// The formatted contents.
+    pub after: String,
+}
+
+impl Change {
+    /// Writes the formatted contents back to the file, encoding them again if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] naming the file if it cannot be written.
+    pub fn write(&self) -> Result<()> {
+        let contents = if rot13::is_rotated(&self.path) {
+            rot13::rotate(&self.after)
+        } else {
+            self.after.clone()
+        };
+        fs::write(&self.path, contents).map_err(|e| Error::from_io(&self.path, e))
+    }
+
+    /// Returns the change as a unified diff, with 3 lines of context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fortune_kind::formatter::Change;
+    /// use std::path::PathBuf;
+    ///
+    /// let change = Change {
+    ///     path: PathBuf::from("quotes"),
+    ///     before: "One \n%\n".to_string(),
+    ///     after: "One\n%\n".to_string(),
+    /// };
+    /// assert_eq!(
+    ///     change.diff(),
+    ///     "--- quotes\n+++ quotes\n@@ -1,2 +1,2 @@\n-One \n+One\n %\n"
+    /// );
+    /// ```
+    pub fn diff(&self) -> String {
+        let before: Vec<&str> = self.before.split_inclusive('\n').collect();
+        let after: Vec<&str> = self.after.split_inclusive('\n').collect();
+        let edits = diff_lines(&before, &after);
+
+        // Group changes that are close enough for their context to overlap
+        let mut hunks: Vec<(usize, usize)> = vec![];
+        for (i, _) in edits.iter().enumerate().filter(|(_, e)| !e.is_keep()) {
+            match hunks.last_mut() {
+                Some((_, last)) if i - *last <= 2 * CONTEXT + 1 => *last = i,
+                _ => hunks.push((i, i)),
+            }
+        }
+
+        let name = self.path.display();
+        let mut out = format!("--- {}\n+++ {}\n", name, name);
+        for (first, last) in hunks {
+            let hunk = &edits[first.saturating_sub(CONTEXT)..(last + 1 + CONTEXT).min(edits.len())];
+            let (a, b) = match hunk[0] {
+                Edit::Keep(a, b) | Edit::Delete(a, b) | Edit::Insert(a, b) => (a, b),
+            };
+            let removed = hunk
+                .iter()
+                .filter(|e| !matches!(e, Edit::Insert(..)))
+                .count();
+            let added = hunk
+                .iter()
+                .filter(|e| !matches!(e, Edit::Delete(..)))
+                .count();
+            out.push_str(&format!(
+                "@@ -{} +{} @@\n",
+                range(a, removed),
+                range(b, added)
+            ));
+            for edit in hunk {
+                let (sign, line) = match *edit {
+                    Edit::Keep(a, _) => (' ', before[a]),
+                    Edit::Delete(a, _) => ('-', before[a]),
+                    Edit::Insert(_, b) => ('+', after[b]),
+                };
+                out.push(sign);
+                out.push_str(line);
+                if !line.ends_with('\n') {
+                    out.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Formats the contents of a fortune file.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::formatter;
+///
+/// assert_eq!(
+///     formatter::format("%\r\nBe kind.  \r\n    -- Someone\r\n\r\n% \r\nTwo. -Samuel Goldwyn"),
+///     "Be kind.\n\t\t-- Someone\n%\nTwo.\n\t\t-- Samuel Goldwyn\n%\n"
+/// );
+/// ```
+pub fn format(contents: &str) -> String {
+    let mut out = String::new();
+    for entry in parser::entries(contents) {
+        let lines: Vec<&str> = entry.text.lines().map(str::trim_end).collect();
+        for line in format_fortune(&lines) {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push_str("%\n");
+    }
+    out
+}
+
+/// Formats the lines of a single fortune, which has no blank lines around it.
+fn format_fortune(lines: &[&str]) -> Vec<String> {
+    let mut out: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    let parts = Parts::of(lines);
+
+    match parts.attribution {
+        Some((start, end)) => {
+            let author = lines[start].trim_start()["--".len()..].trim();
+            out[start] = format!("{}-- {}", ATTRIBUTION_INDENT, author);
+            for line in &mut out[start + 1..end] {
+                *line = format!("{}   {}", ATTRIBUTION_INDENT, line.trim());
+            }
+        }
+        // Translations follow the attribution, so only look further without one
+        None if parts.translation.is_none() => {
+            if let Some(author) =
+                dash_attribution(&mut out).or_else(|| inline_attribution(&mut out))
+            {
+                out.push(format!("{}-- {}", ATTRIBUTION_INDENT, author));
+            }
+        }
+        None => {}
+    }
+    out
+}
+
+/// Takes an attribution written as an indented `- Author` line off the end of `lines`.
+fn dash_attribution(lines: &mut Vec<String>) -> Option<String> {
+    let [.., previous, last] = lines.as_slice() else {
+        return None;
+    };
+    // A list of indented `- item` lines is not an attribution
+    if previous.trim_start().starts_with('-') {
+        return None;
+    }
+    let author = last
+        .strip_prefix([' ', '\t'])?
+        .trim_start()
+        .strip_prefix('-')
+        .filter(|rest| !rest.starts_with('-'))?
+        .trim()
+        .to_string();
+    if !is_name(&author) {
+        return None;
+    }
+    lines.pop();
+    Some(author)
+}
+
+/// Takes an attribution tacked onto the end of the last sentence off `lines`.
+///
+/// This recognises `... never wrong. -Samuel Goldwyn`, including when the name
+/// was wrapped onto a line of its own.
+fn inline_attribution(lines: &mut Vec<String>) -> Option<String> {
+    let last = lines.len() - 1;
+    if let Some((text, author)) = split_inline(&lines[last]) {
+        lines[last] = text;
+        return Some(author);
+    }
+
+    // The name may have been wrapped: `... he'd turn over in his grave. -Samuel` / `Goldwyn`
+    if last == 0 || !is_name(&lines[last]) || lines[last].split(' ').count() > 2 {
+        return None;
+    }
+    let (text, first_name) = split_inline(&lines[last - 1])?;
+    let author = format!("{} {}", first_name, lines[last].trim());
+    lines.pop();
+    lines[last - 1] = text;
+    Some(author)
+}
+
+/// Splits `line` into the text and the author of a trailing ` -Author`.
+fn split_inline(line: &str) -> Option<(String, String)> {
+    let pos = line.rfind(" -")?;
+    let (text, author) = (line[..pos].trim_end(), line[pos + 2..].trim());
+    let ends_sentence = text.ends_with(['.', '!', '?', '"', '\'', ')']);
+    if ends_sentence && is_name(author) {
+        Some((text.to_string(), author.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Returns `true` if `text` looks like a name: a few capitalised words.
+fn is_name(text: &str) -> bool {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    (1..=4).contains(&words.len())
+        && words.iter().all(|word| {
+            let letters = word.strip_suffix('.').unwrap_or(word);
+            letters.chars().next().is_some_and(char::is_uppercase)
+                && letters.chars().all(char::is_alphabetic)
+        })
+}
+
+/// Formats every fortune file under `path`, returning those that would change.
+///
+/// Files named in `preformatted` and embedded collections are skipped, and
+/// nothing is written: see [`Change::write`]. Files that are not UTF-8 are
+/// skipped too, since writing them would re-encode them as UTF-8, which is
+/// left to [`encoding::check_all`](crate::encoding::check_all).
+///
+/// # Errors
+///
+/// Returns an [`Error`] if `path` or a file under it cannot be read.
+pub fn check_all(path: &Path, max_depth: usize, preformatted: &[String]) -> Result<Vec<Change>> {
+    let mut changes = vec![];
+    for source in file::walk(path, max_depth)? {
+        let skipped = source
+            .file_name()
+            .is_some_and(|name| preformatted.iter().any(|p| name == p.as_str()));
+        if skipped || source.starts_with(collection::EMBEDDED_ROOT) {
+            continue;
+        }
+
+        let (before, fallback) = file::read_file_decoded(&source)?;
+        if fallback.is_some() {
+            continue;
+        }
+        let after = format(&before);
+        if before != after {
+            changes.push(Change {
+                path: source,
+                before,
+                after,
+            });
+        }
+    }
+    Ok(changes)
+}
+
+/// A line kept, deleted or inserted, with its position in the old and new lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep(usize, usize),
+    Delete(usize, usize),
+    Insert(usize, usize),
+}
+
+impl Edit {
+    fn is_keep(&self) -> bool {
+        matches!(self, Edit::Keep(..))
+    }
+}
+
+/// Formats a hunk range of a unified diff, counting lines from 1.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Finds the shortest list of edits turning `a` into `b`, with Myers' algorithm.
+///
+/// Lines are compared without trailing whitespace, so a line that only lost
+/// its trailing spaces or CR is paired with its formatted self, and shows up
+/// as a deleted and inserted line rather than throwing off the alignment.
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let same = |x: usize, y: usize| a[x].trim_end() == b[y].trim_end();
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    let mut v = vec![0_isize; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+
+    // The furthest x reached on each diagonal k = x - y, after each round
+    let mut trace = vec![];
+    'search: for d in 0..=(n + m) {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && same(x as usize, y as usize) {
+                (x, y) = (x + 1, y + 1);
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            (x, y) = (x - 1, y - 1);
+            let (i, j) = (x as usize, y as usize);
+            if a[i] == b[j] {
+                edits.push(Edit::Keep(i, j));
+            } else {
+                // Paired, but changed: show it as replaced. Edits are collected backwards.
+                edits.push(Edit::Insert(i, j));
+                edits.push(Edit::Delete(i, j));
+            }
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert(x as usize, prev_y as usize)
+            } else {
+                Edit::Delete(prev_x as usize, y as usize)
+            });
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    edits.reverse();
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let contents = "\u{feff}One\n\n%\n\n%\nTwo\n    -- Someone,\n       somewhere\n%";
+        let formatted = format(contents);
+
+        assert_eq!(
+            formatted,
+            "One\n%\nTwo\n\t\t-- Someone,\n\t\t   somewhere\n%\n"
+        );
+        assert_eq!(format(&formatted), formatted);
+    }
+
+    #[test]
+    fn test_format_keeps_fortune_text() {
+        // Indented code, rules, hyphenated words and list items are not attributions
+        let contents = "    fn main() {}\n  -- not this\nend\n%\n----\nwell-known -it's. -not a name\n%\nBring:\n  - a towel\n%\n";
+
+        assert_eq!(format(contents), contents);
+    }
+
+    #[test]
+    fn test_format_attribution_styles() {
+        assert_eq!(
+            format("Quote.\n\t  - Someone\n%\n"),
+            "Quote.\n\t\t-- Someone\n%\n"
+        );
+        assert_eq!(
+            format("He'd turn over in his grave. -Samuel\nGoldwyn\n%\n"),
+            "He'd turn over in his grave.\n\t\t-- Samuel Goldwyn\n%\n"
+        );
+        assert_eq!(
+            format("Dit.\n-- Someone\n\n[ Said. ]\n%\n"),
+            "Dit.\n\t\t-- Someone\n\n[ Said. ]\n%\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_hunks() {
+        let change = Change {
+            path: PathBuf::from("quotes"),
+            before: "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12 \n".to_string(),
+            after: "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n".to_string(),
+        };
+
+        assert_eq!(
+            change.diff(),
+            "--- quotes\n+++ quotes\n@@ -9,4 +9,4 @@\n 9\n 10\n 11\n-12 \n+12\n"
+        );
+    }
+
+    #[test]
+    fn test_check_all_skips_preformatted() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("ascii-art"), "  /\\ \n /  \\ \n%\n").unwrap();
+        fs::write(dir.path().join("quotes"), "One \n%\n").unwrap();
+
+        let changes = check_all(dir.path(), file::SHALLOW, &["ascii-art".to_string()]).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].after, "One\n%\n");
+
+        changes[0].write().unwrap();
+        // Only the art is left, once it is no longer skipped
+        let changes = check_all(dir.path(), file::SHALLOW, &[]).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, dir.path().join("ascii-art"));
+    }
+
+    #[test]
+    fn test_check_all_skips_non_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("oldtunes"), b"Caf\xe9 \n%\n").unwrap();
+
+        assert!(check_all(dir.path(), file::SHALLOW, &[])
+            .unwrap()
+            .is_empty());
+    }
+}
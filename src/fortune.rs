@@ -5,29 +5,477 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 
 //! A module for retrieving random quotes (or fortune).
+//!
+//! Nothing in here prints or exits: fortunes are handed back as [`Fortune`]
+//! values, and failures as an [`Error`], leaving presentation to the caller.
+use crate::encoding::Fallback;
 use crate::file;
-use crate::random;
+use crate::parser;
+use crate::random::{self, Source};
+use crate::strfile;
+use crate::{Error, Result};
 
-use std::path::Path;
-use std::path::PathBuf;
-use std::process::exit;
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{thread_rng, Rng};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 /// The default maximum length for a short quote.
 const SHORT: usize = 150;
 
-/// Searches for fortunes matching a given string pattern within the specified path.
+/// A single fortune, along with where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fortune {
+    /// The full text of the fortune, without the surrounding `%` delimiters.
+    pub text: String,
+    /// The file the fortune was read from.
+    pub source: PathBuf,
+    /// The position of the fortune within `source`, counting from zero.
+    pub index: usize,
+    /// The fortune itself, without its attribution or translation.
+    pub body: String,
+    /// The author of the fortune, taken from a trailing `-- author` line.
+    ///
+    /// Attributions wrapped onto further indented lines are joined with spaces.
+    pub attribution: Option<String>,
+    /// A translation of the fortune, taken from a trailing `[ ... ]` block.
+    pub translation: Option<String>,
+    /// How `source` was decoded, if it is not valid UTF-8.
+    ///
+    /// Fortunes picked through a `.dat` index only read their own text, so
+    /// this is only set if that text is not UTF-8.
+    pub fallback: Option<Fallback>,
+}
+
+impl Fortune {
+    /// Builds a fortune from a raw entry of a fortune file.
+    ///
+    /// Blank lines around the entry are dropped, but indentation on the first
+    /// line is kept so preformatted fortunes render correctly.
+    fn new(raw: &str, source: &Path, index: usize) -> Self {
+        let text = raw.trim_start_matches(['\n', '\r']).trim_end().to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        let parts = Parts::of(&lines);
+
+        let body = lines[..parts.body].join("\n").trim_end().to_string();
+        let attribution = parts.attribution.map(|(start, end)| {
+            let first = lines[start].trim_start()["--".len()..].trim();
+            std::iter::once(first)
+                .chain(lines[start + 1..end].iter().map(|l| l.trim()))
+                .collect::<Vec<&str>>()
+                .join(" ")
+        });
+        let translation = parts.translation.map(|start| {
+            let block = lines[start..].join("\n");
+            let inner = block.trim().trim_start_matches('[').trim_end_matches(']');
+            inner
+                .lines()
+                .map(str::trim)
+                .collect::<Vec<&str>>()
+                .join("\n")
+                .trim()
+                .to_string()
+        });
+
+        Fortune {
+            text,
+            source: source.to_path_buf(),
+            index,
+            body,
+            attribution: attribution.filter(|a| !a.is_empty()),
+            translation: translation.filter(|t| !t.is_empty()),
+            fallback: None,
+        }
+    }
+
+    /// Returns the text of the fortune with its attribution lines removed.
+    ///
+    /// Any translation is kept, as it appears in the fortune file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fortune_kind::fortune::{search_fortunes, SearchOptions};
+    /// use std::fs;
+    /// use tempfile::tempdir;
+    ///
+    /// let dir = tempdir().unwrap();
+    /// let path = dir.path().join("quotes");
+    /// fs::write(&path, "Mieux vaut tard que jamais!\n\t\t-- Someone\n\n[ Better late than never ]\n%\n").unwrap();
+    ///
+    /// let fortune = &search_fortunes("", &SearchOptions::default(), None, &[path]).unwrap()[0];
+    /// assert_eq!(fortune.body, "Mieux vaut tard que jamais!");
+    /// assert_eq!(fortune.attribution.as_deref(), Some("Someone"));
+    /// assert_eq!(fortune.translation.as_deref(), Some("Better late than never"));
+    /// assert_eq!(
+    ///     fortune.without_attribution(),
+    ///     "Mieux vaut tard que jamais!\n\n[ Better late than never ]"
+    /// );
+    /// ```
+    pub fn without_attribution(&self) -> String {
+        let lines: Vec<&str> = self.text.lines().collect();
+        match self.attribution_lines() {
+            Some(range) => [&lines[..range.start], &lines[range.end..]]
+                .concat()
+                .join("\n"),
+            None => self.text.clone(),
+        }
+    }
+
+    /// Returns the lines of `text` holding the attribution, counting from zero.
+    pub fn attribution_lines(&self) -> Option<Range<usize>> {
+        let lines: Vec<&str> = self.text.lines().collect();
+        Parts::of(&lines).attribution.map(|(start, end)| start..end)
+    }
+
+    /// Returns a fingerprint of the fortune's text, used to recognise it later.
+    ///
+    /// The fingerprint only depends on the text, so it survives the fortune
+    /// moving within its file or to another file.
+    pub fn fingerprint(&self) -> u64 {
+        random::fnv1a(self.text.as_bytes())
+    }
+
+    /// Returns `true` if the fortune is at most `limit` characters long.
+    fn fits(&self, limit: usize) -> bool {
+        self.text.chars().count() <= limit
+    }
+
+    /// Returns `true` if the fortune has an attribution matched by `matcher`.
+    fn is_by(&self, matcher: &RegexMatcher) -> bool {
+        self.attribution
+            .as_ref()
+            .is_some_and(|a| matcher.is_match(a.as_bytes()).unwrap_or(false))
+    }
+}
+
+/// Where the parts of a fortune start and end, as line numbers within its text.
+pub(crate) struct Parts {
+    /// The number of lines before the attribution or translation.
+    pub(crate) body: usize,
+    /// The first and one past the last line of the attribution.
+    pub(crate) attribution: Option<(usize, usize)>,
+    /// The first line of the translation, which runs to the end.
+    pub(crate) translation: Option<usize>,
+}
+
+impl Parts {
+    /// Finds the parts of a fortune from its lines.
+    ///
+    /// A translation is a trailing block in square brackets, as used in
+    /// `translate-me`. An attribution is a line starting with `--` (but not a
+    /// longer rule of dashes), optionally followed by indented continuation
+    /// lines, that ends the fortune or comes right before the translation.
+    /// Neither can make up the whole fortune.
+    pub(crate) fn of(lines: &[&str]) -> Self {
+        let mut end = lines.len();
+
+        let translation = match lines.last() {
+            Some(last) if last.trim_end().ends_with(']') => lines
+                .iter()
+                .rposition(|l| l.trim_start().starts_with('['))
+                .filter(|&start| start > 0),
+            _ => None,
+        };
+        if let Some(start) = translation {
+            end = start;
+            while end > 0 && lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+        }
+
+        let mut attribution = None;
+        for i in (1..end).rev() {
+            let line = lines[i];
+            if line
+                .trim_start()
+                .strip_prefix("--")
+                .is_some_and(|rest| !rest.starts_with('-'))
+            {
+                attribution = Some((i, end));
+                break;
+            }
+            // Only indented lines may continue an attribution.
+            if line.trim().is_empty() || !line.starts_with([' ', '\t']) {
+                break;
+            }
+        }
+
+        Parts {
+            body: attribution.map_or(end, |(start, _)| start),
+            attribution,
+            translation,
+        }
+    }
+}
+
+impl fmt::Display for Fortune {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Serializes every field of the fortune, plus its `length` in characters.
+///
+/// # Examples
 ///
-/// This function reads from the file or directory provided in `path`.
-/// It iterates through all quotes and prints every match found to stdout.
+/// ```
+/// use fortune_kind::fortune::{search_fortunes, SearchOptions};
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let dir = tempdir().unwrap();
+/// let path = dir.path().join("quotes");
+/// fs::write(&path, "Be kind.\n    -- Someone\n%\n").unwrap();
+///
+/// let fortune = &search_fortunes("", &SearchOptions::default(), None, &[path]).unwrap()[0];
+/// let json = serde_json::to_value(fortune).unwrap();
+/// assert_eq!(json["attribution"], "Someone");
+/// assert_eq!(json["index"], 0);
+/// assert_eq!(json["length"], 23);
+/// ```
+impl Serialize for Fortune {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Fortune", 7)?;
+        s.serialize_field("text", &self.text)?;
+        s.serialize_field("body", &self.body)?;
+        s.serialize_field("attribution", &self.attribution)?;
+        s.serialize_field("translation", &self.translation)?;
+        s.serialize_field("source", &self.source)?;
+        s.serialize_field("index", &self.index)?;
+        s.serialize_field("length", &self.text.chars().count())?;
+        s.end()
+    }
+}
+
+/// Options controlling how a search pattern is matched against fortunes.
+///
+/// By default patterns behave like `grep`: they are case sensitive and each
+/// match must lie within a single line of the fortune.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Match regardless of case.
+    pub case_insensitive: bool,
+    /// Only match on word boundaries.
+    pub whole_word: bool,
+    /// Allow matches to span line breaks, with `.` matching newlines too.
+    pub multi_line: bool,
+    /// If set, only fortunes whose attribution matches this regex are
+    /// returned. It is matched regardless of case.
+    pub author: Option<String>,
+}
+
+impl SearchOptions {
+    /// Compiles `pattern` into a matcher honouring these options.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BadPattern`] if `pattern` is not a valid regex.
+    fn matcher(&self, pattern: &str) -> Result<RegexMatcher> {
+        let mut builder = RegexMatcherBuilder::new();
+        builder
+            .case_insensitive(self.case_insensitive)
+            .word(self.whole_word)
+            // `^` and `$` should anchor to lines, not the whole fortune.
+            .multi_line(true)
+            .dot_matches_new_line(self.multi_line);
+        if !self.multi_line {
+            builder.line_terminator(Some(b'\n'));
+        }
+        builder
+            .build(pattern)
+            .map_err(|e| Error::BadPattern(e.to_string()))
+    }
+}
+
+/// Compiles `pattern` into a matcher for the attribution of fortunes.
+///
+/// Author names are matched regardless of case, so `pratchett` finds
+/// `Terry Pratchett`.
+///
+/// # Errors
+///
+/// Returns [`Error::BadPattern`] if `pattern` is not a valid regex.
+fn author_matcher(pattern: &str) -> Result<RegexMatcher> {
+    RegexMatcherBuilder::new()
+        .case_insensitive(true)
+        .build(pattern)
+        .map_err(|e| Error::BadPattern(e.to_string()))
+}
+
+/// Options controlling which fortune [`get_quote`] picks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuoteOptions {
+    /// How short the fortune should be.
+    ///   - `0`: Retrieves a completely random quote.
+    ///   - `1`: Default short size (<= 150 characters, not bytes).
+    ///   - `2-255`: Halves the target length for each increment.
+    ///
+    /// This is a preference: if no quote in the selected file is short
+    /// enough, any quote from it may be returned.
+    pub short: u8,
+    /// If set, only fortunes at most this many characters long are
+    /// considered. Unlike `short`, this is a hard limit.
+    pub max_length: Option<usize>,
+    /// How fortune files are weighted against each other.
+    pub strategy: random::Strategy,
+    /// If set, only fortunes whose attribution matches this regex are
+    /// considered. It is matched regardless of case.
+    pub author: Option<String>,
+    /// Fingerprints (see [`Fortune::fingerprint`]) of fortunes to avoid, such
+    /// as those shown recently. Unlike the other filters, this is ignored if
+    /// it would rule out every fortune.
+    pub exclude: HashSet<u64>,
+}
+
+impl QuoteOptions {
+    /// Returns `true` if these options rule out some fortunes altogether.
+    fn filters(&self) -> bool {
+        self.max_length.is_some() || self.author.is_some() || !self.exclude.is_empty()
+    }
+
+    /// Describes the fortunes these options allow, for [`Error::NoMatch`].
+    fn criteria(&self) -> String {
+        let mut criteria = vec![];
+        if let Some(n) = self.max_length {
+            criteria.push(format!("is at most {} characters long", n));
+        }
+        if let Some(author) = &self.author {
+            criteria.push(format!("has an author matching {:?}", author));
+        }
+        criteria.join(" and ")
+    }
+
+    /// Compiles the hard filters of these options, `max_length` and `author`,
+    /// into a test fortunes must pass.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BadPattern`] if `author` is not a valid regex.
+    fn filter(&self) -> Result<impl Fn(&Fortune) -> bool + '_> {
+        let author = self.author.as_deref().map(author_matcher).transpose()?;
+        Ok(move |q: &Fortune| {
+            self.max_length.map_or(true, |n| q.fits(n))
+                && author.as_ref().map_or(true, |m| q.is_by(m))
+        })
+    }
+}
+
+/// Splits the contents of a fortune file into its fortunes, noting how the
+/// file was decoded on each.
+fn parse(contents: &str, source: &Path, fallback: Option<Fallback>) -> Vec<Fortune> {
+    parser::entries(contents)
+        .enumerate()
+        .map(|(i, entry)| Fortune {
+            fallback,
+            ..Fortune::new(&entry.text, source, i)
+        })
+        .collect()
+}
+
+/// Counts the fortunes in the file at `source`.
+///
+/// Uses the `.dat` index if one is up to date, and scans the file otherwise.
+pub(crate) fn count_fortunes(source: &Path) -> Result<usize> {
+    match strfile::read_index(source) {
+        Some(index) => Ok(index.len()),
+        None => Ok(parser::entries(&file::read_file(source)?).count()),
+    }
+}
+
+/// A fortune file that may be picked from, with its odds of being picked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStats {
+    /// The fortune file.
+    pub path: PathBuf,
+    /// The number of fortunes in the file.
+    pub fortunes: usize,
+    /// The chance of the file being picked, between 0 and 1.
+    pub probability: f64,
+}
+
+/// Lists every fortune file under `sources` with its odds of being picked.
+///
+/// The odds are those used by [`get_quote_from`] when no length filter
+/// applies, see [`random::probabilities`]. Files are sorted by path.
+///
+/// # Errors
+///
+/// * An I/O variant of [`Error`] if any of the files cannot be read.
+/// * [`Error::EmptyCollection`] if the sources contain no fortunes.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::fortune::list_files;
+/// use fortune_kind::random::Strategy;
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let dir = tempdir().unwrap();
+/// fs::write(dir.path().join("a"), "One\n%\n").unwrap();
+/// fs::write(dir.path().join("b"), "One\n%\nTwo\n%\nThree\n%\n").unwrap();
+///
+/// let files = list_files(&[dir.path().to_path_buf().into()], Strategy::EqualFortunes).unwrap();
+/// assert_eq!(files[1].fortunes, 3);
+/// assert_eq!(files[1].probability, 0.75);
+/// ```
+pub fn list_files(sources: &[Source], strategy: random::Strategy) -> Result<Vec<FileStats>> {
+    let mut files = random::probabilities(sources, strategy)?
+        .into_iter()
+        .map(|(probability, path)| {
+            Ok(FileStats {
+                fortunes: count_fortunes(&path)?,
+                path,
+                probability,
+            })
+        })
+        .collect::<Result<Vec<FileStats>>>()?;
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Reads every fortune under `sources`, in a stable order.
+pub(crate) fn read_all_fortunes(sources: &[Source]) -> Result<Vec<Fortune>> {
+    let mut fortunes = vec![];
+    for s in sources {
+        for (source, contents, fallback) in file::read_all_files(&s.path, s.max_depth)? {
+            if !s.excludes(&source) {
+                fortunes.extend(parse(&contents, &source, fallback));
+            }
+        }
+    }
+    Ok(fortunes)
+}
+
+/// Searches for fortunes matching a regex pattern within the specified paths.
+///
+/// This function reads from every file or directory provided in `paths`
+/// and returns every match found.
 ///
 /// # Arguments
 ///
-/// * `pattern` - The string pattern to search for.
-/// * `path` - The file or directory to search in.
+/// * `pattern` - The regex to search for.
+/// * `options` - How `pattern` should be matched, see [`SearchOptions`].
+/// * `max_length` - If set, only fortunes at most this many characters long are returned.
+/// * `paths` - The files or directories to search in.
+///
+/// # Errors
+///
+/// * [`Error::BadPattern`] if `pattern` or the author pattern is not a valid regex.
+/// * An I/O variant of [`Error`] if any of the paths cannot be read.
+/// * [`Error::NoMatch`] if no fortune matches.
 ///
 /// # Examples
 ///
 /// ```
+/// use fortune_kind::fortune::{search_fortunes, SearchOptions};
 /// use std::fs::{self, File};
 /// use std::io::Write;
 /// use tempfile::tempdir;
@@ -38,39 +486,98 @@ const SHORT: usize = 150;
 /// writeln!(file, "Linux\n%\nMac\n%\nLinux\n%").unwrap();
 ///
 /// // Pass the path directly to the function
-/// fortune_kind::fortune::search_fortunes("Linux", &file_path);
+/// let options = SearchOptions::default();
+/// let found = search_fortunes("^Lin.x$", &options, None, &[file_path.clone()]).unwrap();
+/// assert_eq!(found.len(), 2);
+/// assert_eq!(found[1].index, 2);
+///
+/// let missing = search_fortunes("Windows", &options, None, &[file_path.clone()]);
+/// assert!(matches!(missing, Err(fortune_kind::Error::NoMatch(..))));
+///
+/// let invalid = search_fortunes("(Linux", &options, None, &[file_path]);
+/// assert!(matches!(invalid, Err(fortune_kind::Error::BadPattern(_))));
 /// ```
-pub fn search_fortunes(pattern: &str, path: &Path) {
-    // TODO: Handle your errors!
-    let files = file::read_all_files(path).unwrap_or_else(|e| {
-        eprintln!("Error reading fortunes from {:?}: {}", path, e);
-        exit(1);
-    });
+pub fn search_fortunes(
+    pattern: &str,
+    options: &SearchOptions,
+    max_length: Option<usize>,
+    paths: &[PathBuf],
+) -> Result<Vec<Fortune>> {
+    let sources: Vec<Source> = paths.iter().cloned().map(Source::from).collect();
+    search_fortunes_from(pattern, options, max_length, &sources)
+}
 
-    for file in files {
-        let matches = file.split("\n%\n").filter(|x| x.contains(pattern));
-        for fortune in matches {
-            println!("{}\n%", fortune.trim());
-        }
+/// Searches for fortunes matching a regex pattern within the specified sources.
+///
+/// Like [`search_fortunes`], but honours each source's
+/// [`max_depth`](Source::max_depth). Percentages are ignored, since every
+/// match is returned.
+///
+/// # Errors
+///
+/// Same as [`search_fortunes`].
+pub fn search_fortunes_from(
+    pattern: &str,
+    options: &SearchOptions,
+    max_length: Option<usize>,
+    sources: &[Source],
+) -> Result<Vec<Fortune>> {
+    let matcher = options.matcher(pattern)?;
+    // The same filters as picking a fortune, so `-n` and `--author` agree
+    let filters = QuoteOptions {
+        max_length,
+        author: options.author.clone(),
+        ..Default::default()
+    };
+    let keep = filters.filter()?;
+    let paths: Vec<PathBuf> = sources.iter().map(|s| s.path.clone()).collect();
+
+    let found: Vec<Fortune> = read_all_fortunes(sources)?
+        .into_iter()
+        .filter(|f| matcher.is_match(f.text.as_bytes()).unwrap_or(false))
+        .filter(|f| keep(f))
+        .collect();
+
+    if found.is_empty() {
+        let criteria = match filters.criteria() {
+            rest if rest.is_empty() => format!("matches {:?}", pattern),
+            rest => format!("matches {:?} and {}", pattern, rest),
+        };
+        return Err(Error::NoMatch(paths, criteria));
     }
+
+    Ok(found)
 }
 
-/// Retrieves and prints a random quote from the specified path.
+/// Retrieves a random quote from the specified paths.
 ///
 /// Filters out empty strings and handles trailing delimiters safely.
 ///
 /// # Arguments
 ///
-/// * `quote_size` - A reference to a byte determining the target length.
-///   - `1`: Default short size (<= 150 chars).
-///   - `2-254`: Halves the target length for each increment.
-///   - `255`: Prints a humorous message and exits.
-///   - `0`: Retrieves a completely random quote.
-/// * `path` - The path to the fortune file or directory.
+/// * `options` - What kind of fortune to pick, see [`QuoteOptions`].
+/// * `paths` - The fortune files or directories to pick from. Files across
+///   all of them are pooled and weighted by `options.strategy`.
+///
+/// If no fortune in the selected file passes the filters of `options`, the
+/// file is dropped and another one is picked by weight. Recently shown
+/// fortunes are only picked again once every file has been ruled out.
+///
+/// If the selected file has an up to date `.dat` index (see [`strfile`]) and
+/// no length filter applies, the fortune is read by seeking straight to its
+/// offset rather than reading the whole file.
+///
+/// # Errors
+///
+/// * [`Error::BadPattern`] if `options.author` is not a valid regex.
+/// * An I/O variant of [`Error`] if the selected file cannot be read.
+/// * [`Error::EmptyCollection`] if the paths contain no fortunes.
+/// * [`Error::NoMatch`] if `options.max_length` or `options.author` rules out every fortune.
 ///
 /// # Examples
 ///
 /// ```
+/// use fortune_kind::fortune::{get_quote, QuoteOptions};
 /// use std::fs::File;
 /// use std::io::Write;
 /// use tempfile::tempdir;
@@ -80,59 +587,188 @@ pub fn search_fortunes(pattern: &str, path: &Path) {
 /// let mut file = File::create(&file_path).unwrap();
 /// writeln!(file, "Short\n%\nLong...\n%").unwrap();
 ///
-/// fortune_kind::fortune::get_quote(&1, &file_path.to_path_buf());
+/// let options = QuoteOptions {
+///     short: 1,
+///     max_length: Some(80),
+///     ..Default::default()
+/// };
+/// let fortune = get_quote(&options, &[file_path.clone()]).unwrap();
+/// assert_eq!(fortune.source, file_path);
 /// ```
-pub fn get_quote(quote_size: &u8, path: &PathBuf) {
-    //let file = handle_file_errors(fortune_dir, &file::pick_file);
-    let file = match random::get_random_file_weighted(path.clone()) {
-        Ok(f) => f,
-        Err(_) => return, // random module handles printing the error
-    };
+pub fn get_quote(options: &QuoteOptions, paths: &[PathBuf]) -> Result<Fortune> {
+    let sources: Vec<Source> = paths.iter().cloned().map(Source::from).collect();
+    get_quote_from(options, &sources)
+}
 
-    let quotes: Vec<&str> = file
-        .split("\n%\n")
-        .filter(|s| !s.trim().is_empty())
-        .collect();
+/// Retrieves a random quote from the specified sources.
+///
+/// Like [`get_quote`], but each source may claim a fixed percentage of the
+/// picks, as in `fortune 30% fedi 70% pratchett`. See [`random::probabilities`]
+/// for how the percentages combine with `options.strategy`.
+///
+/// # Errors
+///
+/// Same as [`get_quote`].
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::fortune::{get_quote_from, QuoteOptions};
+/// use fortune_kind::random::Source;
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let dir = tempdir().unwrap();
+/// fs::write(dir.path().join("a"), "From a\n%\n").unwrap();
+/// fs::write(dir.path().join("b"), "From b\n%\n").unwrap();
+///
+/// let sources = [
+///     Source { percent: Some(100.0), ..Source::from(dir.path().join("a")) },
+///     Source { percent: Some(0.0), ..Source::from(dir.path().join("b")) },
+/// ];
+/// let fortune = get_quote_from(&QuoteOptions::default(), &sources).unwrap();
+/// assert_eq!(fortune.text, "From a");
+/// ```
+pub fn get_quote_from(options: &QuoteOptions, sources: &[Source]) -> Result<Fortune> {
+    get_quote_with(&mut thread_rng(), options, sources)
+}
 
-    if quotes.is_empty() {
-        return;
-    }
+/// Retrieves a random quote from the specified sources, using `rng` for every choice.
+///
+/// Like [`get_quote_from`], but given the same `rng` state, sources and
+/// options, the same fortune is always picked.
+///
+/// # Errors
+///
+/// Same as [`get_quote`].
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::fortune::{get_quote_with, QuoteOptions};
+/// use fortune_kind::random::seeded;
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let dir = tempdir().unwrap();
+/// fs::write(dir.path().join("quotes"), "One\n%\nTwo\n%\nThree\n%\n").unwrap();
+/// let sources = [dir.path().to_path_buf().into()];
+///
+/// let options = QuoteOptions::default();
+/// let first = get_quote_with(&mut seeded(42), &options, &sources).unwrap();
+/// let again = get_quote_with(&mut seeded(42), &options, &sources).unwrap();
+/// assert_eq!(first, again);
+/// ```
+pub fn get_quote_with<R: Rng + ?Sized>(
+    rng: &mut R,
+    options: &QuoteOptions,
+    sources: &[Source],
+) -> Result<Fortune> {
+    let paths: Vec<PathBuf> = sources.iter().map(|s| s.path.clone()).collect();
+    // Hard filters like `-n` narrow the pool before `-s` gets a say.
+    let keep = options.filter()?;
+    let mut files = random::probabilities(sources, options.strategy)?;
 
-    let mut tmp = vec![];
+    let no_exclude = HashSet::new();
+    let mut exclude = &options.exclude;
+    // Files whose remaining fortunes were all excluded, to fall back on
+    let mut shown = vec![];
+    let mut any_fortunes = false;
 
-    match quote_size {
-        n if n > &0 => {
-            let mut target_length: usize = SHORT;
-            if *n != 1 {
-                for _ in 1..*n {
-                    target_length /= 2;
-                }
-            }
-            if *n == 255_u8 {
-                println!("WE GET IT, YOU WANT A SHORT FORTUNE");
-                exit(0);
-            }
-            if target_length < 1 {
-                target_length = 1;
-            }
-            for q in &quotes {
-                if q.len() <= target_length {
-                    tmp.push(q)
-                }
+    // Files the filters leave nothing in are dropped and another is picked by
+    // weight, so the strategy and percentages hold for every pick.
+    loop {
+        let i = match WeightedIndex::new(files.iter().map(|(p, _)| *p)) {
+            Ok(weights) => weights.sample(rng),
+            // Repeating a recent fortune beats showing nothing
+            Err(_) if !shown.is_empty() => {
+                files = std::mem::take(&mut shown);
+                exclude = &no_exclude;
+                continue;
             }
+            Err(_) if any_fortunes => return Err(Error::NoMatch(paths, options.criteria())),
+            Err(_) => return Err(Error::EmptyCollection(paths)),
+        };
+        let source = files[i].1.clone();
 
-            if tmp.is_empty() {
-                println!("{}", quotes[random::random(quotes.len())]);
-            } else {
-                println!("{}", tmp[random::random(tmp.len())]);
+        if options.short == 0 && !options.filters() {
+            if let Some(fortune) = get_indexed_quote(rng, &source)? {
+                return Ok(fortune);
             }
         }
-        _ => {
-            println!("{}", quotes[random::random(quotes.len())]);
+
+        let (contents, fallback) = file::read_file_decoded(&source)?;
+        let quotes = parse(&contents, &source, fallback);
+        any_fortunes |= !quotes.is_empty();
+        let quotes: Vec<Fortune> = quotes.into_iter().filter(|q| keep(q)).collect();
+        let fresh: Vec<Fortune> = quotes
+            .iter()
+            .filter(|q| !exclude.contains(&q.fingerprint()))
+            .cloned()
+            .collect();
+        if !fresh.is_empty() {
+            return Ok(pick_quote(rng, &options.short, fresh));
+        }
+
+        let file = files.remove(i);
+        if !quotes.is_empty() {
+            shown.push(file);
         }
     }
 }
 
+/// Picks a random quote from `source` using its `.dat` index.
+///
+/// Returns `Ok(None)` if there is no usable index, so the caller can fall back to scanning.
+fn get_indexed_quote<R: Rng + ?Sized>(rng: &mut R, source: &Path) -> Result<Option<Fortune>> {
+    let index = match strfile::read_index(source) {
+        Some(index) if !index.is_empty() => index,
+        _ => return Ok(None),
+    };
+
+    let i = random::random_with(rng, index.len());
+    let raw = match index.fetch(source, i) {
+        // Read files that are not UTF-8 whole, so they are decoded consistently
+        Err(Error::InvalidUtf8(_)) => return Ok(None),
+        raw => raw?,
+    };
+
+    Ok(parse(&raw, source, None)
+        .into_iter()
+        .next()
+        .map(|mut fortune| {
+            fortune.index = i;
+            fortune
+        }))
+}
+
+/// Picks a random quote from `quotes`, preferring ones that match `quote_size`.
+///
+/// Falls back to any quote if none are short enough, so `-s` never comes up empty.
+fn pick_quote<R: Rng + ?Sized>(rng: &mut R, quote_size: &u8, mut quotes: Vec<Fortune>) -> Fortune {
+    if *quote_size > 0 {
+        let mut target_length: usize = SHORT;
+        for _ in 1..*quote_size {
+            target_length /= 2;
+        }
+        if target_length < 1 {
+            target_length = 1;
+        }
+
+        let mut tmp: Vec<Fortune> = quotes
+            .iter()
+            .filter(|q| q.fits(target_length))
+            .cloned()
+            .collect();
+        if !tmp.is_empty() {
+            return tmp.swap_remove(random::random_with(rng, tmp.len()));
+        }
+    }
+
+    let i = random::random_with(rng, quotes.len());
+    quotes.swap_remove(i)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,77 +777,366 @@ mod tests {
     use tempfile::tempdir;
 
     /// Helper to create a temporary fortune file for testing.
-    /// Returns the directory and the specific file path.
-    fn create_mock_fortune_file(content: &str) -> (tempfile::TempDir, PathBuf) {
+    /// Returns the directory and the specific file path, ready to pass as `paths`.
+    fn create_mock_fortune_file(content: &str) -> (tempfile::TempDir, Vec<PathBuf>) {
         let dir = tempdir().unwrap();
         let file_path = dir.path().join("mock_fortunes");
         let mut file = File::create(&file_path).unwrap();
         write!(file, "{}", content).unwrap();
-        (dir, file_path)
+        (dir, vec![file_path])
+    }
+
+    fn short(n: u8) -> QuoteOptions {
+        QuoteOptions {
+            short: n,
+            ..Default::default()
+        }
+    }
+
+    fn max_length(n: usize) -> QuoteOptions {
+        QuoteOptions {
+            max_length: Some(n),
+            ..Default::default()
+        }
     }
 
     #[test]
     fn test_get_quote_respects_short_limit() {
         // Create a file where one quote is very short and one is very long
         let content = "Short\n%\nThis is a very long fortune that definitely exceeds the 150 character limit for short fortunes. It needs to be long enough to ensure the filter catches it correctly.\n%";
-        let (_dir, file_path) = create_mock_fortune_file(content);
+        let (_dir, paths) = create_mock_fortune_file(content);
 
         // We run this multiple times because it's random,
         // but it should NEVER pick the long one.
         for _ in 0..10 {
-            // Note: In our current get_quote, we print to stdout.
-            // In a deeper refactor, we'd return a String, but for now,
-            // we just ensure it doesn't panic.
-            get_quote(&1, &file_path);
+            assert_eq!(get_quote(&short(1), &paths).unwrap().text, "Short");
         }
     }
 
     #[test]
     fn test_get_quote_empty_file_graceful_exit() {
-        let (_dir, file_path) = create_mock_fortune_file("");
+        let (_dir, paths) = create_mock_fortune_file("");
 
         // Should not panic
-        get_quote(&0, &file_path);
+        assert!(matches!(
+            get_quote(&short(0), &paths),
+            Err(Error::EmptyCollection(_))
+        ));
     }
 
     #[test]
     fn test_get_quote_only_delimiters() {
-        let (_dir, file_path) = create_mock_fortune_file("\n%\n\n%\n");
+        let (_dir, paths) = create_mock_fortune_file("\n%\n\n%\n");
 
         // Should handle gracefully without panic
-        get_quote(&0, &file_path);
+        assert!(matches!(
+            get_quote(&short(0), &paths),
+            Err(Error::EmptyCollection(_))
+        ));
     }
 
     #[test]
     fn test_search_fortunes_finds_multiple() {
         let content = "Target One\n%\nNo Match\n%\nTarget Two\n%";
-        let (_dir, file_path) = create_mock_fortune_file(content);
+        let (_dir, paths) = create_mock_fortune_file(content);
 
         // This ensures the logic for .filter() works over .find()
-        search_fortunes("Target", &file_path);
+        let found = search_fortunes("Target", &SearchOptions::default(), None, &paths).unwrap();
+        let texts: Vec<&str> = found.iter().map(|f| f.text.as_str()).collect();
+        assert_eq!(texts, ["Target One", "Target Two"]);
+        assert_eq!(found[0].index, 0);
+        assert_eq!(found[1].index, 2);
+        assert_eq!(found[0].source, paths[0]);
     }
 
     #[test]
     fn test_target_length_math() {
         // This tests the logic: 1 -> 150, 2 -> 75, 3 -> 37...
-        // We can't easily check stdout here, but we verify the code path
-        // for different n values doesn't crash.
+        // We verify the code path for different n values doesn't crash.
         let content = "A\n%\nB\n%";
-        let (_dir, file_path) = create_mock_fortune_file(content);
+        let (_dir, paths) = create_mock_fortune_file(content);
 
-        get_quote(&1, &file_path); // target 150
-        get_quote(&2, &file_path); // target 75
-        get_quote(&8, &file_path); // target 1 (lowest clamp)
+        get_quote(&short(1), &paths).unwrap(); // target 150
+        get_quote(&short(2), &paths).unwrap(); // target 75
+        get_quote(&short(8), &paths).unwrap(); // target 1 (lowest clamp)
     }
 
     #[test]
     fn test_get_quote_fallback_logic() {
         // If we ask for a short quote (len < 5) but only have long ones
         let content = "This is a long quote\n%";
-        let (_dir, file_path) = create_mock_fortune_file(content);
+        let (_dir, paths) = create_mock_fortune_file(content);
 
         // Requested -ss... (very short), should fallback to the long quote
         // instead of panicking.
-        get_quote(&10, &file_path);
+        assert!(get_quote(&short(10), &paths).is_ok());
+    }
+
+    #[test]
+    fn test_fits_counts_chars_not_bytes() {
+        let (_dir, paths) = create_mock_fortune_file("ææææ\n%\n");
+        let fortune = get_quote(&short(0), &paths).unwrap();
+
+        assert!(fortune.fits(4));
+        assert!(!fortune.fits(3));
+    }
+
+    #[test]
+    fn test_get_quote_length_falls_back_to_other_files() {
+        let dir = tempdir().unwrap();
+        let mut long = File::create(dir.path().join("long")).unwrap();
+        write!(long, "{}\n%\n", "x".repeat(500)).unwrap();
+        let mut short = File::create(dir.path().join("short")).unwrap();
+        write!(short, "Tiny\n%\n").unwrap();
+
+        // The long file is almost always picked, so this exercises the fallback.
+        for _ in 0..10 {
+            let fortune = get_quote(&max_length(10), &[dir.path().to_path_buf()]).unwrap();
+            assert_eq!(fortune.text, "Tiny");
+        }
+    }
+
+    #[test]
+    fn test_get_quote_filters_keep_percentages() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("long"), format!("{}\n%\n", "x".repeat(50))).unwrap();
+        std::fs::write(dir.path().join("short"), "Tiny\n%\n").unwrap();
+        let sources = [
+            Source {
+                percent: Some(100.0),
+                ..Source::from(dir.path().join("long"))
+            },
+            Source {
+                percent: Some(0.0),
+                ..Source::from(dir.path().join("short"))
+            },
+        ];
+
+        // A source at 0% is never a fallback
+        assert!(matches!(
+            get_quote_from(&max_length(10), &sources),
+            Err(Error::NoMatch(..))
+        ));
+
+        // Once its fortunes were all shown, the long file repeats one
+        let long = get_quote_from(&short(0), &sources).unwrap();
+        let options = QuoteOptions {
+            exclude: HashSet::from([long.fingerprint()]),
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            assert_eq!(get_quote_from(&options, &sources).unwrap(), long);
+        }
+    }
+
+    #[test]
+    fn test_get_quote_length_errors_when_nothing_fits() {
+        let (_dir, paths) = create_mock_fortune_file("Far too long\n%\n");
+
+        assert!(matches!(
+            get_quote(&max_length(3), &paths),
+            Err(Error::NoMatch(..))
+        ));
+    }
+
+    #[test]
+    fn test_fortune_attribution() {
+        let content = "Be kind.\n    -- Someone\n%\nNo author here\n%\n";
+        let (_dir, paths) = create_mock_fortune_file(content);
+
+        let found = search_fortunes("", &SearchOptions::default(), None, &paths).unwrap();
+        assert_eq!(found[0].attribution.as_deref(), Some("Someone"));
+        assert_eq!(found[1].attribution, None);
+    }
+
+    #[test]
+    fn test_fortune_parts() {
+        let fortune = |raw: &str| Fortune::new(raw, Path::new("quotes"), 0);
+
+        let wrapped = fortune("Quote.\n\t\t-- Terry Pratchett,\n\t\t   \"Mort\"");
+        assert_eq!(wrapped.body, "Quote.");
+        assert_eq!(
+            wrapped.attribution.as_deref(),
+            Some("Terry Pratchett, \"Mort\"")
+        );
+        assert_eq!(wrapped.without_attribution(), "Quote.");
+
+        let translated = fortune("Carpe diem.\n\n[ Seize the day,\n  or the fish ]");
+        assert_eq!(translated.body, "Carpe diem.");
+        assert_eq!(translated.attribution, None);
+        assert_eq!(
+            translated.translation.as_deref(),
+            Some("Seize the day,\nor the fish")
+        );
+
+        // Rules of dashes and lone attributions are part of the body
+        let rule = fortune("Heading\n------------");
+        assert_eq!(rule.attribution, None);
+        assert_eq!(rule.body, rule.text);
+        assert_eq!(fortune("-- Nobody").attribution, None);
+    }
+
+    #[test]
+    fn test_author_filter() {
+        let content = "One\n    -- Terry Pratchett\n%\nTwo\n    -- Someone\n%\nThree\n%\n";
+        let (_dir, paths) = create_mock_fortune_file(content);
+        let options = QuoteOptions {
+            author: Some("pratchett".to_string()),
+            ..Default::default()
+        };
+
+        for _ in 0..10 {
+            assert_eq!(get_quote(&options, &paths).unwrap().body, "One");
+        }
+
+        let search = SearchOptions {
+            author: Some("^some".to_string()),
+            ..Default::default()
+        };
+        let found = search_fortunes("", &search, None, &paths).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].body, "Two");
+
+        let nobody = QuoteOptions {
+            author: Some("Nobody".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            get_quote(&nobody, &paths),
+            Err(Error::NoMatch(..))
+        ));
+    }
+
+    #[test]
+    fn test_get_quote_excludes_fingerprints() {
+        let (_dir, paths) = create_mock_fortune_file("One\n%\nTwo\n%\nThree\n%\n");
+        let all = search_fortunes("", &SearchOptions::default(), None, &paths).unwrap();
+        let options = QuoteOptions {
+            exclude: all[..2].iter().map(Fortune::fingerprint).collect(),
+            ..Default::default()
+        };
+
+        for _ in 0..10 {
+            assert_eq!(get_quote(&options, &paths).unwrap().text, "Three");
+        }
+
+        // Excluding everything falls back to repeating
+        let options = QuoteOptions {
+            exclude: all.iter().map(Fortune::fingerprint).collect(),
+            ..Default::default()
+        };
+        assert!(get_quote(&options, &paths).is_ok());
+    }
+
+    #[test]
+    fn test_search_fortunes_options() {
+        let content = "Kindness\n%\nBe kind\n%\nKIND words\nlast forever\n%\n";
+        let (_dir, paths) = create_mock_fortune_file(content);
+        let texts = |pattern: &str, options: SearchOptions| -> Vec<String> {
+            match search_fortunes(pattern, &options, None, &paths) {
+                Ok(found) => found.into_iter().map(|f| f.text).collect(),
+                Err(Error::NoMatch(..)) => vec![],
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        };
+
+        assert_eq!(texts("kind", SearchOptions::default()), ["Be kind"]);
+
+        let insensitive = SearchOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(texts("kind", insensitive).len(), 3);
+
+        let word = SearchOptions {
+            case_insensitive: true,
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(texts("kind", word), ["Be kind", "KIND words\nlast forever"]);
+
+        // Matches stay within a line unless multi_line is set
+        assert!(texts("words.last", SearchOptions::default()).is_empty());
+        let multi = SearchOptions {
+            multi_line: true,
+            ..Default::default()
+        };
+        assert_eq!(texts("words.last", multi), ["KIND words\nlast forever"]);
+    }
+
+    #[test]
+    fn test_search_fortunes_bad_pattern() {
+        let (_dir, paths) = create_mock_fortune_file("Anything\n%\n");
+
+        assert!(matches!(
+            search_fortunes("[unclosed", &SearchOptions::default(), None, &paths),
+            Err(Error::BadPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_quote_uses_index() {
+        let (_dir, paths) = create_mock_fortune_file("One\n%\nTwo\n%\nThree\n%\n");
+        strfile::write_index(&paths[0]).unwrap();
+
+        let all = search_fortunes("", &SearchOptions::default(), None, &paths).unwrap();
+        for _ in 0..10 {
+            let fortune = get_quote(&short(0), &paths).unwrap();
+            assert_eq!(fortune, all[fortune.index]);
+        }
+    }
+
+    #[test]
+    fn test_index_agrees_with_parser_on_variants() {
+        let (_dir, paths) = create_mock_fortune_file("\u{feff}%\r\nOne\r\n% \r\n\r\nTwo\r\n%");
+        strfile::write_index(&paths[0]).unwrap();
+
+        let all = search_fortunes("", &SearchOptions::default(), None, &paths).unwrap();
+        let texts: Vec<&str> = all.iter().map(|f| f.text.as_str()).collect();
+        assert_eq!(texts, ["One", "Two"]);
+        for _ in 0..10 {
+            let fortune = get_quote(&short(0), &paths).unwrap();
+            assert_eq!(fortune, all[fortune.index]);
+        }
+    }
+
+    #[test]
+    fn test_fortunes_note_fallback_encoding() {
+        let (_dir, paths) = create_mock_fortune_file("");
+        std::fs::write(&paths[0], b"Caf\xe9\n%\n").unwrap();
+
+        let fortune = get_quote(&short(0), &paths).unwrap();
+        assert_eq!(fortune.text, "Café");
+        assert_eq!(fortune.fallback.unwrap().line, 1);
+        let found = search_fortunes("", &SearchOptions::default(), None, &paths).unwrap();
+        assert!(found[0].fallback.is_some());
+    }
+
+    #[test]
+    fn test_get_quote_skips_files_without_fortunes() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("delimiters"), "\n%\n%\n%\n%\n%\n").unwrap();
+        std::fs::write(dir.path().join("quotes"), "One\n%\n").unwrap();
+        let sources = [Source::from(dir.path().to_path_buf())];
+
+        for seed in 0..20 {
+            for strategy in [random::Strategy::BySize, random::Strategy::EqualFiles] {
+                let options = QuoteOptions {
+                    strategy,
+                    ..Default::default()
+                };
+                let fortune = get_quote_with(&mut random::seeded(seed), &options, &sources);
+                assert_eq!(fortune.unwrap().text, "One");
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_quote_ignores_stale_index() {
+        let (_dir, paths) = create_mock_fortune_file("Old\n%\n");
+        strfile::write_index(&paths[0]).unwrap();
+        std::fs::write(&paths[0], "Brand new\n%\n").unwrap();
+
+        assert_eq!(get_quote(&short(0), &paths).unwrap().text, "Brand new");
     }
 }
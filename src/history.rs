@@ -0,0 +1,166 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for remembering which fortunes were shown recently.
+//!
+//! The history is a plain text file with one [`Fortune::fingerprint`] per
+//! line, oldest first, kept in `$XDG_STATE_HOME/fortune-kind` by default.
+//! Only fingerprints are stored, never the fortunes themselves.
+use crate::fortune::Fortune;
+use crate::{Error, Result};
+
+use std::collections::{HashSet, VecDeque};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The fingerprints of the most recently shown fortunes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct History {
+    /// Fingerprints, oldest first.
+    fingerprints: VecDeque<u64>,
+}
+
+impl History {
+    /// Loads the history stored at `path`.
+    ///
+    /// A missing file is an empty history, and malformed lines are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] naming `path` if it exists but cannot be read.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(History::default()),
+            Err(e) => return Err(Error::from_io(path, e)),
+        };
+
+        let fingerprints = contents
+            .lines()
+            .filter_map(|line| u64::from_str_radix(line.trim(), 16).ok())
+            .collect();
+        Ok(History { fingerprints })
+    }
+
+    /// Writes the history to `path`, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] naming `path` if it cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::from_io(dir, e))?;
+        }
+        let contents: String = self
+            .fingerprints
+            .iter()
+            .map(|f| format!("{:016x}\n", f))
+            .collect();
+        fs::write(path, contents).map_err(|e| Error::from_io(path, e))
+    }
+
+    /// Returns the fingerprints of the last `window` fortunes shown.
+    pub fn recent(&self, window: usize) -> HashSet<u64> {
+        self.fingerprints
+            .iter()
+            .rev()
+            .take(window)
+            .copied()
+            .collect()
+    }
+
+    /// Records that `fortune` was shown, keeping only the last `window` fortunes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fortune_kind::fortune::{search_fortunes, SearchOptions};
+    /// use fortune_kind::history::History;
+    /// use std::fs;
+    /// use tempfile::tempdir;
+    ///
+    /// let dir = tempdir().unwrap();
+    /// let path = dir.path().join("quotes");
+    /// fs::write(&path, "One\n%\nTwo\n%\n").unwrap();
+    /// let fortunes = search_fortunes("", &SearchOptions::default(), None, &[path]).unwrap();
+    ///
+    /// let mut history = History::default();
+    /// history.record(&fortunes[0], 1);
+    /// history.record(&fortunes[1], 1);
+    /// assert!(!history.recent(1).contains(&fortunes[0].fingerprint()));
+    /// assert!(history.recent(1).contains(&fortunes[1].fingerprint()));
+    /// ```
+    pub fn record(&mut self, fortune: &Fortune, window: usize) {
+        self.fingerprints.push_back(fortune.fingerprint());
+        while self.fingerprints.len() > window {
+            self.fingerprints.pop_front();
+        }
+    }
+}
+
+/// Returns the directory fortune-kind keeps its state in.
+///
+/// This is `$XDG_STATE_HOME/fortune-kind`, falling back to
+/// `$HOME/.local/state/fortune-kind`. Returns `None` if neither variable is set.
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(base.join("fortune-kind"))
+}
+
+/// Returns the default location of the history file, inside [`state_dir`].
+pub fn default_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join("history"))
+}
+
+/// Forgets every fortune shown, by removing the history file at `path`.
+///
+/// # Errors
+///
+/// Returns an [`Error`] naming `path` if it exists but cannot be removed.
+pub fn clear(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::from_io(path, e)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state").join("history");
+        let history = History {
+            fingerprints: [1, u64::MAX, 42].into(),
+        };
+
+        history.save(&path).unwrap();
+        assert_eq!(History::load(&path).unwrap(), history);
+
+        clear(&path).unwrap();
+        assert_eq!(History::load(&path).unwrap(), History::default());
+        // Clearing twice is fine
+        clear(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_skips_malformed_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history");
+        fs::write(&path, "000000000000002a\nnot hex\n\n").unwrap();
+
+        assert_eq!(
+            History::load(&path).unwrap().recent(10),
+            HashSet::from([42])
+        );
+    }
+}
@@ -6,7 +6,20 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 
 pub mod cli;
+pub mod collection;
+pub mod config;
+pub mod encoding;
+pub mod error;
+pub mod formatter;
 pub mod fortune;
+pub mod history;
+pub mod lint;
+pub mod parser;
 // These are used internally by fortune.rs, so they live here.
 pub(crate) mod file;
 pub mod random;
+pub mod rot13;
+pub mod shuffle;
+pub mod strfile;
+
+pub use error::{Error, Result};
@@ -0,0 +1,307 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for checking fortune files for mistakes.
+//!
+//! Each problem found is a [`Diagnostic`] pointing at a line of a file. Errors
+//! are problems that make fortunes be read wrongly, such as CRLF line endings
+//! or a delimiter with trailing spaces. The [`parser`](crate::parser) copes
+//! with these, but other fortune programs glue neighbouring fortunes together.
+//! Warnings are untidy but harmless, and notes are merely unusual.
+use crate::file;
+use crate::Result;
+
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Unusual, but most likely intended.
+    Note,
+    /// Untidy, but fortunes are still read correctly.
+    Warning,
+    /// Fortunes are read wrongly by other fortune programs, or not at all.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a fortune file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// The fortune file.
+    pub path: PathBuf,
+    /// The line the problem is on, starting at 1.
+    pub line: usize,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// A short name for the kind of problem, e.g. `trailing-whitespace`.
+    pub code: &'static str,
+    /// A description of the problem.
+    pub message: String,
+}
+
+/// Formats the diagnostic like a compiler would: `path:line: severity: message [code]`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {} [{}]",
+            self.path.display(),
+            self.line,
+            self.severity,
+            self.message,
+            self.code
+        )
+    }
+}
+
+/// Checks the contents of the fortune file at `path`.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::lint::{self, Severity};
+/// use std::path::Path;
+///
+/// let found = lint::check(Path::new("quotes"), "One \n%\nTwo\r\n%\r\n");
+/// assert_eq!(found[0].line, 1);
+/// assert_eq!(found[0].code, "trailing-whitespace");
+/// assert_eq!(found[1].severity, Severity::Error);
+/// assert_eq!(found[1].code, "crlf");
+/// ```
+pub fn check(path: &Path, contents: &str) -> Vec<Diagnostic> {
+    let mut found = vec![];
+    let mut report = |line, severity, code, message: &str| {
+        found.push(Diagnostic {
+            path: path.to_path_buf(),
+            line,
+            severity,
+            code,
+            message: message.to_string(),
+        })
+    };
+
+    let mut crlf = vec![];
+    let mut fortunes = 0;
+    // Lines of the current entry, and whether the last one was blank
+    let mut text_lines = 0;
+    let mut blank_lines = 0;
+    let mut last_blank = false;
+    let mut last_line = 0;
+
+    for (i, raw) in contents.split_inclusive('\n').enumerate() {
+        let n = i + 1;
+        last_line = n;
+        let line = raw.strip_suffix('\n').unwrap_or(raw);
+        let line = match line.strip_suffix('\r') {
+            Some(line) => {
+                crlf.push(n);
+                line
+            }
+            None => line,
+        };
+
+        if line.trim_end() == "%" {
+            if line != "%" {
+                report(
+                    n,
+                    Severity::Error,
+                    "delimiter-whitespace",
+                    "delimiter has trailing whitespace, which other fortune programs read as text",
+                );
+            }
+            if n == 1 {
+                report(
+                    n,
+                    Severity::Error,
+                    "leading-delimiter",
+                    "file starts with a delimiter, which other fortune programs read as text",
+                );
+            } else if text_lines == 0 {
+                report(n, Severity::Warning, "empty-entry", "empty fortune");
+            } else if last_blank {
+                report(
+                    n - 1,
+                    Severity::Warning,
+                    "blank-before-delimiter",
+                    "blank line before delimiter",
+                );
+            }
+            fortunes += usize::from(text_lines > 0);
+            (text_lines, blank_lines, last_blank) = (0, 0, false);
+            continue;
+        }
+
+        if line.starts_with('%') {
+            report(
+                n,
+                Severity::Warning,
+                "stray-percent",
+                "line starts with % but is not a delimiter",
+            );
+        }
+        if line.len() != line.trim_end().len() {
+            report(
+                n,
+                Severity::Warning,
+                "trailing-whitespace",
+                "trailing whitespace",
+            );
+        }
+        last_blank = line.trim().is_empty();
+        if last_blank {
+            blank_lines += 1;
+        } else {
+            text_lines += 1;
+        }
+    }
+
+    if text_lines > 0 {
+        fortunes += 1;
+        report(
+            last_line,
+            Severity::Warning,
+            "missing-final-delimiter",
+            "last fortune is not followed by a delimiter",
+        );
+    } else if blank_lines > 0 {
+        report(
+            last_line + 1 - blank_lines,
+            Severity::Warning,
+            "trailing-blank-lines",
+            "blank lines after the last delimiter",
+        );
+    }
+    if let Some(&first) = crlf.first() {
+        report(
+            first,
+            Severity::Error,
+            "crlf",
+            &format!(
+                "{} line(s) end with CRLF, so other fortune programs miss delimiters",
+                crlf.len()
+            ),
+        );
+    }
+    match fortunes {
+        0 => report(
+            1,
+            Severity::Error,
+            "no-fortunes",
+            "file contains no fortunes",
+        ),
+        1 => report(
+            1,
+            Severity::Note,
+            "single-fortune",
+            "file contains a single fortune",
+        ),
+        _ => {}
+    }
+
+    found.sort_by_key(|d| d.line);
+    found
+}
+
+/// Checks every fortune file under `path`, descending `max_depth` levels of
+/// directories as for [`Collection::walk`](crate::collection::Collection::walk).
+///
+/// Files that are not valid UTF-8 are reported as an error diagnostic, and
+/// checked as decoded by [`encoding::decode`](crate::encoding::decode).
+///
+/// # Errors
+///
+/// Returns an [`Error`](crate::Error) if `path` or a file under it cannot be read.
+pub fn check_all(path: &Path, max_depth: usize) -> Result<Vec<Diagnostic>> {
+    let mut found = vec![];
+    for source in file::walk(path, max_depth)? {
+        let (contents, fallback) = file::read_file_decoded(&source)?;
+        if let Some(fallback) = fallback {
+            found.push(Diagnostic {
+                path: source.clone(),
+                line: fallback.line,
+                severity: Severity::Error,
+                code: "invalid-utf8",
+                message: format!(
+                    "file is not valid UTF-8 and is read as {}, run `fortune-kind convert` to re-encode it",
+                    fallback.encoding
+                ),
+            });
+        }
+        found.extend(check(&source, &contents));
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn codes(contents: &str) -> Vec<(usize, &'static str)> {
+        check(Path::new("quotes"), contents)
+            .into_iter()
+            .map(|d| (d.line, d.code))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_file() {
+        assert_eq!(codes("One\n    -- Someone\n%\nTwo\n%\n"), []);
+    }
+
+    #[test]
+    fn test_delimiter_problems() {
+        assert_eq!(
+            codes("%\nOne\n%\n%\nTwo\n\n% \nThree\n%%\n%\n"),
+            [
+                (1, "leading-delimiter"),
+                (4, "empty-entry"),
+                (6, "blank-before-delimiter"),
+                (7, "delimiter-whitespace"),
+                (9, "stray-percent"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_end_of_file_problems() {
+        assert_eq!(codes("One\n%\nTwo"), [(3, "missing-final-delimiter")]);
+        assert_eq!(codes("One\n%\nTwo\n%\n\n\n"), [(5, "trailing-blank-lines")]);
+        assert_eq!(codes("One\n%\n"), [(1, "single-fortune")]);
+        assert_eq!(codes("%\n"), [(1, "leading-delimiter"), (1, "no-fortunes")]);
+    }
+
+    #[test]
+    fn test_crlf_reported_once() {
+        let found = check(Path::new("quotes"), "One\r\n%\r\nTwo\r\n%\r\n");
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Error);
+        assert!(found[0].message.starts_with("4 line(s)"));
+    }
+
+    #[test]
+    fn test_check_all_reports_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("latin1"), b"One\n%\ncaf\xe9\n%\n").unwrap();
+
+        let found = check_all(dir.path(), file::SHALLOW).unwrap();
+        assert_eq!((found[0].line, found[0].code), (3, "invalid-utf8"));
+        assert!(found[0].message.contains("Windows-1252"));
+    }
+}
@@ -3,48 +3,535 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-only
 
+use clap::ArgMatches;
+use serde::Serialize;
+use std::collections::HashSet;
 use std::env;
 use std::fs;
-use std::io;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
+use std::process::exit;
 
 // Import modules from our own library crate
 use fortune_kind::cli;
-use fortune_kind::fortune;
+use fortune_kind::collection;
+use fortune_kind::config::{self, Color, Config, Format};
+use fortune_kind::encoding;
+use fortune_kind::formatter;
+use fortune_kind::fortune::{self, Fortune};
+use fortune_kind::history::{self, History};
+use fortune_kind::lint::{self, Severity};
+use fortune_kind::random::{self, Date, Source, Strategy};
+use fortune_kind::rot13;
+use fortune_kind::shuffle;
+use fortune_kind::strfile;
+use fortune_kind::{Error, Result};
 
-fn main() -> io::Result<()> {
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        if let Error::NotFound(_) = e {
+            eprintln!("Hint: Check your FORTUNE_PATH and FORTUNE_DIR environment variables and config file, or provide a valid path as an argument.");
+        }
+        exit(e.exit_code());
+    }
+}
+
+fn run() -> Result<()> {
     let matches = cli::build_cli().get_matches();
 
-    // Determine the path with absolute path resolution:
-    // CLI Argument -> Canonicalize to absolute path
-    // Unkind Flag -> Env Var -> Manifest Dir/off
-    // Default -> Env Var -> Manifest Dir/fortunes
-    let path = if let Some(p) = matches.get_one::<String>("path") {
-        let p = PathBuf::from(p);
-        fs::canonicalize(&p).unwrap_or(p)
-    } else if matches.get_flag("unkind") {
-        env::var("FORTUNE_OFF_DIR")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("off"))
+    // Subcommands only read the config file when they need it, so a broken
+    // config can still be worked around with explicit paths.
+    if let Some(("index", sub)) = matches.subcommand() {
+        let paths: Vec<PathBuf> = match sub.get_many::<String>("path") {
+            Some(p) => p.map(PathBuf::from).collect(),
+            None => local_paths(&load_config()?),
+        };
+        return index(&paths, max_depth(sub));
+    }
+
+    if let Some(("lint", sub)) = matches.subcommand() {
+        let paths: Vec<PathBuf> = match sub.get_many::<String>("path") {
+            Some(p) => p.map(PathBuf::from).collect(),
+            None => local_paths(&load_config()?),
+        };
+        let format = sub
+            .get_one::<String>("format")
+            .expect("format has a default")
+            .parse()
+            .expect("format is validated by clap");
+        return lint(
+            &paths,
+            max_depth(sub),
+            format,
+            sub.get_flag("deny-warnings"),
+        );
+    }
+
+    if let Some(("fmt", sub)) = matches.subcommand() {
+        let config = load_config()?;
+        let paths: Vec<PathBuf> = match sub.get_many::<String>("path") {
+            Some(p) => p.map(PathBuf::from).collect(),
+            None => local_paths(&config),
+        };
+        let preformatted = config.preformatted.clone().unwrap_or_else(|| {
+            formatter::PREFORMATTED
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        });
+        let mut changes = vec![];
+        for path in &paths {
+            changes.extend(formatter::check_all(path, max_depth(sub), &preformatted)?);
+        }
+        return format_files(&changes, sub.get_flag("check"), sub.get_flag("diff"));
+    }
+
+    if let Some(("convert", sub)) = matches.subcommand() {
+        let paths: Vec<PathBuf> = match sub.get_many::<String>("path") {
+            Some(p) => p.map(PathBuf::from).collect(),
+            None => local_paths(&load_config()?),
+        };
+        let mut conversions = vec![];
+        for path in &paths {
+            conversions.extend(encoding::check_all(path, max_depth(sub))?);
+        }
+        return convert(&conversions, sub.get_flag("check"));
+    }
+
+    if let Some(("rot13", sub)) = matches.subcommand() {
+        for file in sub.get_many::<String>("file").into_iter().flatten() {
+            let file = PathBuf::from(file);
+            println!("{:?} -> {:?}", file, rot13::toggle(&file)?);
+        }
+        return Ok(());
+    }
+
+    if let Some(("history", sub)) = matches.subcommand() {
+        if let (Some(("clear", _)), Some(path)) = (sub.subcommand(), history::default_path()) {
+            history::clear(&path)?;
+        }
+        return Ok(());
+    }
+
+    let config = load_config()?;
+
+    // Determine the sources with absolute path resolution:
+    // CLI Arguments -> Percentages and paths, canonicalized to absolute paths
+    // All Flag -> Both of the below, pooled together
+    // Unkind Flag -> Env Var -> Config -> Data Dirs/off
+    // Default -> Env Vars -> Config -> Data Dirs/fortunes
+    let depth = max_depth(&matches);
+    let with_settings = |mut source: Source| {
+        source.max_depth = depth;
+        source.exclude = config.exclude.clone();
+        source
+    };
+    let sources: Vec<Source> = if let Some(args) = matches.get_many::<String>("path") {
+        let mut sources = Source::parse_args(args)?;
+        if matches.get_flag("shuffle") && sources.iter().any(|s| s.percent.is_some()) {
+            return Err(Error::BadWeights(
+                "--shuffle shows every fortune once, so it cannot be combined with percentages"
+                    .to_string(),
+            ));
+        }
+        for source in &mut sources {
+            if let Ok(p) = fs::canonicalize(&source.path) {
+                source.path = p;
+            }
+        }
+        sources.into_iter().map(with_settings).collect()
     } else {
-        env::var("FORTUNE_DIR")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fortunes"))
+        let mut groups = vec![];
+        if !matches.get_flag("unkind") {
+            groups.push(kind_paths(&config));
+        }
+        if matches.get_flag("all") || matches.get_flag("unkind") {
+            groups.push(unkind_paths(&config));
+        }
+        // Earlier directories of a search path shadow later ones, but kind and
+        // unkind fortunes are only pooled, never shadow each other.
+        let mut sources = vec![];
+        for paths in groups {
+            let mut group: Vec<Source> = search_path(paths)
+                .into_iter()
+                .map(|p| with_settings(Source::from(p)))
+                .collect();
+            if group.iter().all(|s| collection::exists(&s.path)) {
+                random::shadow(&mut group)?;
+            }
+            sources.extend(group);
+        }
+        sources
     };
+    let paths: Vec<PathBuf> = sources.iter().map(|s| s.path.clone()).collect();
 
-    // THE CATCH: Check if path exists before proceeding
-    if !path.exists() {
-        eprintln!("Error: The fortune path {:?} was not found.", path);
-        eprintln!("Hint: Check your FORTUNE_DIR environment variable or provide a valid path as an argument.");
-        std::process::exit(1);
+    // THE CATCH: Check if paths exist before proceeding
+    for path in &paths {
+        if !collection::exists(path) {
+            return Err(Error::NotFound(path.clone()));
+        }
     }
 
-    if let Some(pattern) = matches.get_one::<String>("find") {
-        fortune::search_fortunes(pattern, &path);
+    let length = matches.get_one::<usize>("length").copied();
+    // `-n 0` turns every length limit off
+    let max_length = length.filter(|&n| n > 0);
+    let author = matches.get_one::<String>("author").cloned();
+    let no_attribution = matches.get_flag("no-attribution");
+    let format = match matches.get_one::<String>("format") {
+        // clap only accepts valid formats
+        Some(f) => f.parse().expect("format is validated by clap"),
+        None => config.format.unwrap_or_default(),
+    };
+    let color = use_color(&matches, &config);
+    let strategy = if matches.get_flag("equal-files") {
+        Strategy::EqualFiles
+    } else if matches.get_flag("equal-fortunes") {
+        Strategy::EqualFortunes
+    } else if matches.get_flag("by-size") {
+        Strategy::BySize
+    } else {
+        config.strategy.unwrap_or_default()
+    };
+
+    if matches.get_flag("list") {
+        for file in fortune::list_files(&sources, strategy)? {
+            println!(
+                "{:6.2}% {:>6}  {}",
+                file.probability * 100.0,
+                file.fortunes,
+                file.path.display()
+            );
+        }
+    } else if let Some(pattern) = matches.get_one::<String>("find") {
+        let options = fortune::SearchOptions {
+            case_insensitive: matches.get_flag("ignore-case"),
+            whole_word: matches.get_flag("word"),
+            multi_line: matches.get_flag("multiline"),
+            author,
+        };
+        let found: Vec<Fortune> =
+            fortune::search_fortunes_from(pattern, &options, max_length, &sources)?
+                .into_iter()
+                .map(|f| {
+                    if no_attribution {
+                        strip_attribution(f)
+                    } else {
+                        f
+                    }
+                })
+                .collect();
+        warn_fallbacks(&found);
+        match format {
+            Format::Json => println!("{}", to_json(&found, true)),
+            Format::Ndjson => found.iter().for_each(|f| println!("{}", to_json(f, false))),
+            Format::Plain => found
+                .iter()
+                .for_each(|f| println!("{}\n%", paint(f, color))),
+        }
     } else {
         let short_count = matches.get_count("short");
-        fortune::get_quote(&short_count, &path);
+        if short_count == 255_u8 {
+            println!("WE GET IT, YOU WANT A SHORT FORTUNE");
+            return Ok(());
+        }
+        // An explicit -n replaces the config's short, and --shuffle goes
+        // through every fortune, so it is ignored there too
+        let short = match (short_count, length) {
+            (0, None) => config.short.unwrap_or(0),
+            (n, _) => n,
+        };
+        // The history is opt-in: without a window, nothing is read or written.
+        let window = matches.get_one::<usize>("no-repeat-window").copied();
+        let history_path = window.and_then(|_| history::default_path());
+        if window.is_some() && history_path.is_none() {
+            eprintln!("Warning: Neither XDG_STATE_HOME nor HOME is set, so no history is kept.");
+        }
+        let mut history = match &history_path {
+            Some(path) => History::load(path)?,
+            None => History::default(),
+        };
+
+        let options = fortune::QuoteOptions {
+            short,
+            max_length,
+            strategy,
+            author,
+            exclude: history.recent(window.unwrap_or(0)),
+        };
+        let bag_path = if matches.get_flag("shuffle") {
+            let path = shuffle::bag_path(&sources);
+            if path.is_none() {
+                eprintln!("Warning: Neither XDG_STATE_HOME nor HOME is set, so fortunes are picked at random.");
+            }
+            path
+        } else {
+            None
+        };
+        let mut fortune = match (bag_path, seed(&matches)?) {
+            (Some(path), _) => shuffle::next(&sources, &path)?,
+            (None, Some(seed)) => {
+                fortune::get_quote_with(&mut random::seeded(seed), &options, &sources)?
+            }
+            (None, None) => fortune::get_quote_from(&options, &sources)?,
+        };
+        if let (Some(path), Some(window)) = (&history_path, window) {
+            history.record(&fortune, window);
+            history.save(path)?;
+        }
+        warn_fallbacks(std::slice::from_ref(&fortune));
+        if no_attribution {
+            fortune = strip_attribution(fortune);
+        }
+        match format {
+            Format::Json => println!("{}", to_json(&fortune, true)),
+            Format::Ndjson => println!("{}", to_json(&fortune, false)),
+            Format::Plain => println!("{}", paint(&fortune, color)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the configuration file, if there is one.
+fn load_config() -> Result<Config> {
+    match config::default_path() {
+        Some(path) => Config::load(&path),
+        None => Ok(Config::default()),
+    }
+}
+
+/// Reads the seed for the random choices from `--seed`, or from `--daily` and `--date`.
+///
+/// Returns `None` if the choices should not be seeded.
+fn seed(matches: &ArgMatches) -> Result<Option<u64>> {
+    if let Some(&seed) = matches.get_one::<u64>("seed") {
+        return Ok(Some(seed));
+    }
+
+    let date = match matches.get_one::<String>("date") {
+        Some(date) => date.parse()?,
+        None if matches.get_flag("daily") => Date::today(),
+        None => return Ok(None),
+    };
+    let salt = matches.get_one::<String>("salt").map_or("", |s| s.as_str());
+    Ok(Some(random::daily_seed(&date, salt)))
+}
+
+/// Warns once about each file of `fortunes` that is not valid UTF-8.
+fn warn_fallbacks(fortunes: &[Fortune]) {
+    let mut warned = HashSet::new();
+    for fortune in fortunes {
+        if let Some(fallback) = fortune.fallback {
+            if warned.insert(&fortune.source) {
+                eprintln!(
+                    "Warning: {}:{}: not valid UTF-8, read as {}. Run `fortune-kind convert` to re-encode it.",
+                    fortune.source.display(),
+                    fallback.line,
+                    fallback.encoding
+                );
+            }
+        }
+    }
+}
+
+/// Removes the attribution from `fortune`, both from its text and its fields.
+fn strip_attribution(fortune: Fortune) -> Fortune {
+    Fortune {
+        text: fortune.without_attribution(),
+        attribution: None,
+        ..fortune
+    }
+}
+
+/// Decides whether to colour output: `--color` -> `NO_COLOR` -> Config -> whether stdout is a terminal
+fn use_color(matches: &ArgMatches, config: &Config) -> bool {
+    let color = match matches.get_one::<String>("color") {
+        Some(c) => c.parse().expect("color is validated by clap"),
+        None if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) => Color::Never,
+        None => config.color.unwrap_or_default(),
+    };
+    match color {
+        Color::Always => true,
+        Color::Never => false,
+        Color::Auto => io::stdout().is_terminal(),
+    }
+}
+
+/// Returns the text of `fortune`, with its attribution dimmed if `color` is set.
+fn paint(fortune: &Fortune, color: bool) -> String {
+    let range = match fortune.attribution_lines() {
+        Some(range) if color => range,
+        _ => return fortune.text.clone(),
+    };
+    fortune
+        .text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if range.contains(&i) {
+                format!("\x1b[2m{}\x1b[0m", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Serializes `value` as JSON, over several lines if `pretty` is set.
+fn to_json<T: Serialize + ?Sized>(value: &T, pretty: bool) -> String {
+    let json = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    json.expect("fortunes always serialize to JSON")
+}
+
+/// Reads how many levels of directories to descend from `--recursive` and `--max-depth`.
+fn max_depth(matches: &ArgMatches) -> usize {
+    match matches.get_one::<usize>("max-depth") {
+        Some(&n) => n,
+        None if matches.get_flag("recursive") => usize::MAX,
+        // Only the directory itself
+        None => 1,
     }
+}
 
+/// Writes a `.dat` index next to every fortune file under `paths`.
+fn index(paths: &[PathBuf], max_depth: usize) -> Result<()> {
+    for path in paths {
+        for (source, index) in strfile::write_indexes(path, max_depth)? {
+            println!("{:?}: {} fortunes", strfile::dat_path(&source), index.len());
+        }
+    }
     Ok(())
 }
+
+/// Checks every fortune file under `paths`, printing what is found in `format`.
+///
+/// Fails if any errors are found, or any warnings if `deny_warnings` is set.
+fn lint(paths: &[PathBuf], max_depth: usize, format: Format, deny_warnings: bool) -> Result<()> {
+    let mut found = vec![];
+    for path in paths {
+        found.extend(lint::check_all(path, max_depth)?);
+    }
+
+    match format {
+        Format::Json => println!("{}", to_json(&found, true)),
+        Format::Ndjson => found.iter().for_each(|d| println!("{}", to_json(d, false))),
+        Format::Plain => found.iter().for_each(|d| println!("{}", d)),
+    }
+
+    let failing = if deny_warnings {
+        Severity::Warning
+    } else {
+        Severity::Error
+    };
+    match found.iter().filter(|d| d.severity >= failing).count() {
+        0 => Ok(()),
+        n => Err(Error::LintFailed(n)),
+    }
+}
+
+/// Rewrites each changed file, or with `check` or `diff` set, lists or shows the changes.
+fn format_files(changes: &[formatter::Change], check: bool, diff: bool) -> Result<()> {
+    for change in changes {
+        if diff {
+            print!("{}", change.diff());
+        } else if check {
+            println!("{}", change.path.display());
+        } else {
+            change.write()?;
+            println!("{:?}: formatted", change.path);
+        }
+    }
+
+    if (check || diff) && !changes.is_empty() {
+        return Err(Error::Unformatted(changes.len()));
+    }
+    Ok(())
+}
+
+/// Re-encodes the files in `conversions` as UTF-8, or with `check` only lists them.
+///
+/// Fails with `check` if there is anything to convert.
+fn convert(conversions: &[encoding::Conversion], check: bool) -> Result<()> {
+    for conversion in conversions {
+        let (path, fallback) = (&conversion.path, conversion.fallback);
+        if check {
+            println!(
+                "{}:{}: {}",
+                path.display(),
+                fallback.line,
+                fallback.encoding
+            );
+        } else {
+            conversion.write()?;
+            println!("{:?}: converted from {}", path, fallback.encoding);
+        }
+    }
+
+    if check && !conversions.is_empty() {
+        return Err(Error::Unconverted(conversions.len()));
+    }
+    Ok(())
+}
+
+/// Resolves the paths of kind fortunes: `FORTUNE_PATH` -> `FORTUNE_DIR` -> Config -> Data Dirs/fortunes
+fn kind_paths(config: &Config) -> Vec<PathBuf> {
+    if let Some(path) = env::var_os("FORTUNE_PATH").filter(|v| !v.is_empty()) {
+        return env::split_paths(&path)
+            .filter(|p| !p.as_os_str().is_empty())
+            .collect();
+    }
+    match (env::var("FORTUNE_DIR"), &config.paths) {
+        (Ok(dir), _) => vec![PathBuf::from(dir)],
+        (Err(_), Some(paths)) => paths.clone(),
+        (Err(_), None) => installed("fortunes"),
+    }
+}
+
+/// Resolves the paths of unkind fortunes: Env Var -> Config -> Data Dirs/off
+fn unkind_paths(config: &Config) -> Vec<PathBuf> {
+    match (env::var("FORTUNE_OFF_DIR"), &config.unkind_paths) {
+        (Ok(dir), _) => vec![PathBuf::from(dir)],
+        (Err(_), Some(paths)) => paths.clone(),
+        (Err(_), None) => installed("off"),
+    }
+}
+
+/// Returns the installed copies of the `collection` collection, as a search path.
+fn installed(collection: &str) -> Vec<PathBuf> {
+    config::data_dirs()
+        .into_iter()
+        .map(|dir| dir.join(collection))
+        .collect()
+}
+
+/// Resolves the kind fortune directories on disk, for subcommands that write to them.
+///
+/// Embedded collections cannot be written to, so they are skipped.
+fn local_paths(config: &Config) -> Vec<PathBuf> {
+    search_path(kind_paths(config))
+        .into_iter()
+        .filter(|p| !p.starts_with(collection::EMBEDDED_ROOT))
+        .collect()
+}
+
+/// Drops the directories of a search path that do not exist, like `PATH` lookups do.
+///
+/// If none of them exist, all are kept so the first one is reported as missing.
+fn search_path(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let existing: Vec<PathBuf> = paths
+        .iter()
+        .filter(|p| collection::exists(p))
+        .cloned()
+        .collect();
+    if existing.is_empty() {
+        paths
+    } else {
+        existing
+    }
+}
@@ -0,0 +1,225 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for reading the `%`-delimited fortune file format.
+//!
+//! Fortunes are separated by lines holding a single `%`. The [`Parser`] reads
+//! them one line at a time from any reader, and is lenient about the variants
+//! found in the wild:
+//!
+//! * CRLF line endings, which are read as LF.
+//! * A UTF-8 byte order mark at the start of the file, which is skipped.
+//! * Delimiter lines with trailing whitespace, such as `% `.
+//! * A delimiter on the first line, or a last delimiter without a newline.
+//! * Empty or blank entries, which are skipped and do not count as fortunes.
+//! * Bytes that are not UTF-8, which are read as described in
+//!   [`encoding::decode`]. Offsets and lengths count the bytes in the file.
+use crate::encoding;
+
+use std::io::{self, BufRead, BufReader, Read};
+
+/// The byte order mark some editors put at the start of UTF-8 files.
+const BOM: &str = "\u{feff}";
+
+/// A fortune read from a fortune file, along with where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The text of the fortune, with LF line endings and without the blank
+    /// lines around it or the delimiter after it.
+    pub text: String,
+    /// The byte offset the entry starts at, right after the previous delimiter
+    /// line, as recorded in strfile indexes.
+    pub offset: usize,
+    /// The length in bytes of the text of the fortune as stored in the file,
+    /// not counting line endings other than those between its lines.
+    pub len: usize,
+    /// The line the text of the fortune starts on, counting from 1.
+    pub line: usize,
+}
+
+/// An iterator over the fortunes of a fortune file, read line by line.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::parser::Parser;
+///
+/// let contents = "\u{feff}%\r\nOne\r\n% \r\n\r\nTwo\nlines\n%";
+/// let entries: Vec<_> = Parser::new(contents.as_bytes())
+///     .collect::<std::io::Result<_>>()
+///     .unwrap();
+///
+/// assert_eq!(entries[0].text, "One");
+/// assert_eq!((entries[0].offset, entries[0].line), (6, 2));
+/// assert_eq!(entries[1].text, "Two\nlines");
+/// assert_eq!((entries[1].offset, entries[1].line), (15, 5));
+/// ```
+#[derive(Debug)]
+pub struct Parser<R> {
+    reader: R,
+    buf: Vec<u8>,
+    /// The byte offset of the next line.
+    offset: usize,
+    /// The number of lines read so far.
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Parser<R> {
+    /// Creates a parser reading from `reader`.
+    pub fn new(reader: R) -> Self {
+        Parser {
+            reader,
+            buf: vec![],
+            offset: 0,
+            line: 0,
+            done: false,
+        }
+    }
+
+    /// Reads the next line, without its line ending, along with its length
+    /// in bytes in the file.
+    ///
+    /// Returns `None` at the end of the input.
+    fn next_line(&mut self) -> io::Result<Option<(String, usize)>> {
+        self.buf.clear();
+        let read = self.reader.read_until(b'\n', &mut self.buf)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.offset += read;
+        self.line += 1;
+
+        let mut line: &[u8] = &self.buf;
+        line = line.strip_suffix(b"\n").unwrap_or(line);
+        line = line.strip_suffix(b"\r").unwrap_or(line);
+        if self.line == 1 {
+            line = line.strip_prefix(BOM.as_bytes()).unwrap_or(line);
+        }
+        let (text, _) = encoding::decode(line.to_vec());
+        Ok(Some((text, line.len())))
+    }
+}
+
+impl<R: BufRead> Iterator for Parser<R> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let offset = self.offset;
+            let mut lines: Vec<(String, usize)> = vec![];
+            let mut first_line = 0;
+
+            loop {
+                let (line, len) = match self.next_line() {
+                    Ok(Some(line)) => line,
+                    Ok(None) => {
+                        self.done = true;
+                        break;
+                    }
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                };
+                if line.trim_end() == "%" {
+                    break;
+                }
+                // Blank lines before the text are dropped
+                if lines.is_empty() && line.trim().is_empty() {
+                    continue;
+                }
+                if lines.is_empty() {
+                    first_line = self.line;
+                }
+                lines.push((line, len));
+            }
+
+            while lines.last().is_some_and(|(l, _)| l.trim().is_empty()) {
+                lines.pop();
+            }
+            if !lines.is_empty() {
+                let texts: Vec<&str> = lines.iter().map(|(l, _)| l.as_str()).collect();
+                return Some(Ok(Entry {
+                    text: texts.join("\n"),
+                    offset,
+                    len: lines.iter().map(|(_, len)| len).sum::<usize>() + lines.len() - 1,
+                    line: first_line,
+                }));
+            }
+        }
+        None
+    }
+}
+
+/// Reads the fortunes from `reader`, see [`Parser`].
+pub fn parse<R: Read>(reader: R) -> Parser<BufReader<R>> {
+    Parser::new(BufReader::new(reader))
+}
+
+/// Yields the fortunes of a fortune file already read into memory.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::parser;
+///
+/// let texts: Vec<String> = parser::entries("One\n%\n%\nTwo\n%\n").map(|e| e.text).collect();
+/// assert_eq!(texts, ["One", "Two"]);
+/// ```
+pub fn entries(contents: &str) -> impl Iterator<Item = Entry> + '_ {
+    Parser::new(contents.as_bytes()).map(|entry| entry.expect("reading from memory cannot fail"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(contents: &str) -> Vec<String> {
+        entries(contents).map(|e| e.text).collect()
+    }
+
+    #[test]
+    fn test_variants_read_alike() {
+        let expected = ["One", "Two\n\nlines"];
+
+        assert_eq!(texts("One\n%\nTwo\n\nlines\n%\n"), expected);
+        assert_eq!(texts("One\r\n%\r\nTwo\r\n\r\nlines\r\n%\r\n"), expected);
+        assert_eq!(texts("%\nOne\n%\nTwo\n\nlines\n%"), expected);
+        assert_eq!(texts("One\n%  \nTwo\n\nlines\n%\t\n"), expected);
+        assert_eq!(texts("\u{feff}One\n%\nTwo\n\nlines"), expected);
+        assert_eq!(texts("\n\nOne\n\n%\n\n%\nTwo\n\nlines\n%\n\n"), expected);
+    }
+
+    #[test]
+    fn test_keeps_fortune_text() {
+        // Indentation, trailing spaces and lines merely starting with % are text
+        assert_eq!(
+            texts("    indented  \n%%\n%1 off\n%\n"),
+            ["    indented  \n%%\n%1 off"]
+        );
+        assert_eq!(texts(""), Vec::<String>::new());
+        assert_eq!(texts("%\n%\n"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_offsets_and_lines() {
+        let contents = "One\n%\n\n%\n\nThree\n%\n";
+        let found: Vec<(usize, usize)> = entries(contents).map(|e| (e.offset, e.line)).collect();
+
+        assert_eq!(found, [(0, 1), (9, 6)]);
+        assert_eq!(&contents[9..], "\nThree\n%\n");
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let entries: Vec<Entry> = parse(&b"One\r\n%\r\ncaf\xe9\r\nau lait\r\n%\r\n"[..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert_eq!(entries[1].text, "café\nau lait");
+        assert_eq!((entries[1].offset, entries[1].len), (8, 12));
+    }
+}
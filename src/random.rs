@@ -7,15 +7,55 @@
 //! A module for generating random numbers and performing weighted file selection.
 //!
 //! This module utilizes the `rand` crate to provide uniform distribution for index
-//! selection and weighted distribution for file picking based on file size.
+//! selection and weighted distribution for file picking, according to a [`Strategy`].
 
 use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
 use rand::thread_rng;
-use rand::Rng;
-use std::io::Read;
-use std::path::PathBuf;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::file::get_file_sizes;
+use crate::file::{self, get_file_sizes, read_file};
+use crate::fortune::count_fortunes;
+use crate::{Error, Result};
+
+/// How fortune files are weighted against each other when picking one at random.
+///
+/// In configuration files, strategies are named like the command line flags:
+/// `by-size`, `equal-files` and `equal-fortunes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Weight files by their size in bytes, like the original `fortune`.
+    #[default]
+    BySize,
+    /// Give every file holding fortunes the same chance, like `fortune -e`.
+    EqualFiles,
+    /// Weight files by the number of fortunes they hold, so that every
+    /// fortune in the collection is equally likely.
+    EqualFortunes,
+}
+
+impl Strategy {
+    /// Returns the selection weight of the file at `path`, which is `size` bytes long.
+    ///
+    /// # Errors
+    ///
+    /// [`Strategy::EqualFiles`] and [`Strategy::EqualFortunes`] read the file
+    /// (or its `.dat` index) to count its fortunes, and return an [`Error`] if
+    /// that fails.
+    pub fn weight(&self, size: u64, path: &Path) -> Result<u64> {
+        match self {
+            Strategy::BySize => Ok(size),
+            Strategy::EqualFiles => Ok(u64::from(size > 0 && count_fortunes(path)? > 0)),
+            Strategy::EqualFortunes => Ok(count_fortunes(path)? as u64),
+        }
+    }
+}
 
 /// Generates a random number between 0 (inclusive) and the given upper bound (exclusive).
 ///
@@ -40,57 +80,501 @@ use crate::file::get_file_sizes;
 /// assert!(num < 10);
 /// ```
 pub fn random(i: usize) -> usize {
-    let mut rng = thread_rng();
+    random_with(&mut thread_rng(), i)
+}
+
+/// Generates a random number in the range `[0, i)` using `rng`.
+///
+/// # Panics
+///
+/// This function will panic if `i` is 0, like [`random`].
+pub fn random_with<R: Rng + ?Sized>(rng: &mut R, i: usize) -> usize {
     rng.gen_range(0..i)
 }
 
-/// Selects a random file from the given path, weighted by file size, and returns its contents.
+/// Returns a random number generator seeded with `seed`.
+///
+/// The same seed always yields the same sequence of numbers for a given
+/// release of fortune-kind, but the sequence may change between releases.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::random::{random_with, seeded};
+///
+/// let a: Vec<usize> = (0..5).map(|_| random_with(&mut seeded(42), 100)).collect();
+/// let b: Vec<usize> = (0..5).map(|_| random_with(&mut seeded(42), 100)).collect();
+/// assert_eq!(a, b);
+/// ```
+pub fn seeded(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// A calendar date, used to pick the same fortune for a whole day.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::random::Date;
+///
+/// let date: Date = "2024-02-29".parse().unwrap();
+/// assert_eq!(date.to_string(), "2024-02-29");
+/// assert!("2023-02-29".parse::<Date>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    /// The year, e.g. `2024`.
+    pub year: i32,
+    /// The month, from 1 to 12.
+    pub month: u32,
+    /// The day of the month, from 1.
+    pub day: u32,
+}
+
+impl Date {
+    /// Returns the current date in UTC.
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Date::from_days((secs / 86_400) as i64)
+    }
+
+    /// Returns the date `days` days after 1970-01-01.
+    fn from_days(days: i64) -> Self {
+        // Howard Hinnant's `civil_from_days`, with eras of 400 years.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Date { year, month, day }
+    }
+
+    /// Returns the number of days in the month of this date.
+    fn days_in_month(&self) -> u32 {
+        match self.month {
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = Error;
+
+    /// Parses a date of the form `YYYY-MM-DD`.
+    fn from_str(s: &str) -> Result<Self> {
+        let bad = || Error::BadDate(s.to_string());
+        let mut parts = s.splitn(3, '-');
+        let mut next = || parts.next().ok_or_else(bad);
+        let (year, month, day) = (next()?, next()?, next()?);
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return Err(bad());
+        }
+
+        let date = Date {
+            year: year.parse().map_err(|_| bad())?,
+            month: month.parse().map_err(|_| bad())?,
+            day: day.parse().map_err(|_| bad())?,
+        };
+        if !(1..=12).contains(&date.month) || !(1..=date.days_in_month()).contains(&date.day) {
+            return Err(bad());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Returns the seed for the fortune of the day on `date`.
+///
+/// Seeding [`seeded`] with it picks the same fortune all day from the same
+/// collection, and a different one the next day. A different `salt` gives a
+/// different pick for the same day, so teams sharing a collection need not
+/// share a fortune.
+///
+/// The seed is an FNV-1a hash, so it does not depend on the platform or on
+/// the version of Rust.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::random::{daily_seed, Date};
+///
+/// let date: Date = "2024-05-04".parse().unwrap();
+/// assert_eq!(daily_seed(&date, ""), daily_seed(&date, ""));
+/// assert_ne!(daily_seed(&date, ""), daily_seed(&date, "ops"));
+/// ```
+pub fn daily_seed(date: &Date, salt: &str) -> u64 {
+    fnv1a(format!("{}\0{}", date, salt).as_bytes())
+}
+
+/// Hashes `bytes` with 64 bit FNV-1a, which is stable across platforms and releases.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(FNV_OFFSET, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// A fortune file or directory to pick from, optionally with a fixed share of the picks.
+///
+/// This mirrors classic fortune's `fortune 30% fedi 70% pratchett` syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    /// The fortune file or directory.
+    pub path: PathBuf,
+    /// The percentage of picks that should come from `path`, if fixed.
+    pub percent: Option<f64>,
+    /// How many levels of directories below `path` to look for fortune files.
+    /// `1` (the default) only looks at `path` itself, `usize::MAX` has no limit.
+    pub max_depth: usize,
+    /// Names of fortune files to skip when `path` is a directory, e.g. `ascii-art`.
+    pub exclude: Vec<String>,
+    /// Fortune files to skip when `path` is a directory, as paths relative to
+    /// `path`, because an earlier source has them. See [`shadow`].
+    pub shadowed: Vec<PathBuf>,
+}
+
+impl From<PathBuf> for Source {
+    fn from(path: PathBuf) -> Self {
+        Source {
+            path,
+            percent: None,
+            max_depth: file::SHALLOW,
+            exclude: vec![],
+            shadowed: vec![],
+        }
+    }
+}
+
+impl Source {
+    /// Returns `true` if `file`, found under this source, should be skipped.
+    ///
+    /// A file is only skipped if it was found in a directory: naming an
+    /// excluded file as the source itself still reads it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fortune_kind::random::Source;
+    /// use std::path::{Path, PathBuf};
+    ///
+    /// let source = Source {
+    ///     exclude: vec!["ascii-art".to_string()],
+    ///     ..Source::from(PathBuf::from("fortunes"))
+    /// };
+    /// assert!(source.excludes(Path::new("fortunes/ascii-art")));
+    /// assert!(!source.excludes(Path::new("fortunes/fedi")));
+    /// ```
+    pub fn excludes(&self, file: &Path) -> bool {
+        if file == self.path {
+            return false;
+        }
+        file.file_name()
+            .is_some_and(|name| self.exclude.iter().any(|e| name == e.as_str()))
+            || file
+                .strip_prefix(&self.path)
+                .is_ok_and(|relative| self.shadowed.iter().any(|s| s == relative))
+    }
+
+    /// Parses command line arguments of the form `[N%] PATH...` into sources.
+    ///
+    /// The percentage may be a separate argument or prefixed to the path, as
+    /// in `30% fedi` or `30%fedi`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BadWeights`] if a percentage is malformed or dangling,
+    /// or if the percentages add up to more than 100.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fortune_kind::random::Source;
+    ///
+    /// let sources = Source::parse_args(["30%", "fedi", "pratchett"]).unwrap();
+    /// assert_eq!(sources[0].percent, Some(30.0));
+    /// assert_eq!(sources[1].percent, None);
+    ///
+    /// assert!(Source::parse_args(["60%fedi", "50%", "pratchett"]).is_err());
+    /// ```
+    pub fn parse_args<I, S>(args: I) -> Result<Vec<Source>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut sources = vec![];
+        let mut pending: Option<f64> = None;
+
+        for arg in args {
+            let arg = arg.as_ref();
+            let (percent, path) = match arg.split_once('%') {
+                Some((n, rest)) if !n.is_empty() && n.parse::<f64>().is_ok() => {
+                    (Some(parse_percent(n)?), rest)
+                }
+                _ => (None, arg),
+            };
+
+            if let Some(p) = percent {
+                if pending.is_some() {
+                    return Err(Error::BadWeights(format!(
+                        "percentage before {:?} has no path",
+                        arg
+                    )));
+                }
+                pending = Some(p);
+            }
+            if !path.is_empty() {
+                sources.push(Source {
+                    percent: pending.take(),
+                    ..Source::from(PathBuf::from(path))
+                });
+            }
+        }
+
+        if pending.is_some() {
+            return Err(Error::BadWeights(
+                "the last percentage has no path".to_string(),
+            ));
+        }
+
+        let total: f64 = sources.iter().filter_map(|s| s.percent).sum();
+        if total > 100.0 {
+            return Err(Error::BadWeights(format!(
+                "percentages add up to {}%, more than 100%",
+                total
+            )));
+        }
+
+        Ok(sources)
+    }
+}
+
+/// Parses the number in front of a `%`.
+fn parse_percent(n: &str) -> Result<f64> {
+    match n.parse::<f64>() {
+        Ok(p) if (0.0..=100.0).contains(&p) => Ok(p),
+        _ => Err(Error::BadWeights(format!(
+            "{}% is not a percentage between 0 and 100",
+            n
+        ))),
+    }
+}
+
+/// Hides files that are shadowed by a file at the same path in an earlier source.
+///
+/// This gives a search path its usual meaning: with `~/fortunes` listed before
+/// `/usr/share/fortune-kind/fortunes`, a personal `fedi` replaces the packaged
+/// one instead of being pooled with it. Files are compared by their path
+/// relative to their source, so in nested directories `a/sub/fedi` only hides
+/// `b/sub/fedi`, not `b/other/fedi`. A source naming a file shadows files of
+/// that name at the top of later sources.
+///
+/// Shadowed paths are added to the [`shadowed`](Source::shadowed) list of the
+/// later sources, so like exclusions they only apply to files found in
+/// directories.
+///
+/// # Errors
+///
+/// Returns [`Error::NotFound`] (or another I/O variant) if a source cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::random::{self, Source};
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let (user, system) = (tempdir().unwrap(), tempdir().unwrap());
+/// fs::write(user.path().join("fedi"), "Mine\n%\n").unwrap();
+/// fs::write(system.path().join("fedi"), "Packaged\n%\n").unwrap();
+/// fs::write(system.path().join("pratchett"), "Packaged\n%\n").unwrap();
+///
+/// let mut sources = [user.path().to_path_buf().into(), system.path().to_path_buf().into()];
+/// random::shadow(&mut sources).unwrap();
+/// assert!(sources[1].excludes(&system.path().join("fedi")));
+/// assert!(!sources[1].excludes(&system.path().join("pratchett")));
+/// ```
+pub fn shadow(sources: &mut [Source]) -> Result<()> {
+    let mut seen: Vec<PathBuf> = vec![];
+    for source in sources {
+        let relative: Vec<PathBuf> = file::walk(&source.path, source.max_depth)?
+            .iter()
+            .filter(|path| !source.excludes(path))
+            .filter_map(|path| match path.strip_prefix(&source.path) {
+                Ok(relative) if !relative.as_os_str().is_empty() => Some(relative),
+                // The source is the file itself
+                _ => path.file_name().map(Path::new),
+            })
+            .map(Path::to_path_buf)
+            .collect();
+        source.shadowed.extend(seen.iter().cloned());
+        seen.extend(relative);
+    }
+    Ok(())
+}
+
+/// Computes the probability of each file under `sources` being picked.
+///
+/// Sources with a fixed percentage get exactly that share, split among their
+/// files by `strategy`. The remaining share is split by `strategy` across the
+/// files of all other sources, pooled together. If every source has a fixed
+/// percentage, the percentages are scaled up to add up to 100.
+///
+/// # Returns
+///
+/// A vector of `(probability, path)` pairs, with probabilities adding up to 1,
+/// in a stable order.
+///
+/// # Errors
+///
+/// * Returns [`Error::NotFound`] (or another I/O variant) if a path cannot be read.
+/// * Returns [`Error::EmptyCollection`] if no file has a non-zero weight.
+pub fn probabilities(sources: &[Source], strategy: Strategy) -> Result<Vec<(f64, PathBuf)>> {
+    let weigh = |source: &Source| -> Result<Vec<(f64, PathBuf)>> {
+        let mut files = get_file_sizes(&source.path, source.max_depth)?;
+        files.retain(|(_, path)| !source.excludes(path));
+        // Ensure stable sorting for the weighted picker
+        files.sort_unstable_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        files
+            .into_iter()
+            .map(|(size, path)| Ok((strategy.weight(size, &path)? as f64, path)))
+            .collect()
+    };
+
+    let fixed: f64 = sources.iter().filter_map(|s| s.percent).sum();
+    let mut shares = vec![];
+    let mut pooled = vec![];
+    for source in sources {
+        match source.percent {
+            Some(p) => shares.push((p, weigh(source)?)),
+            None => pooled.extend(weigh(source)?),
+        }
+    }
+    if !pooled.is_empty() {
+        shares.push((100.0 - fixed, pooled));
+    }
+
+    let mut files = vec![];
+    for (share, group) in shares {
+        let total: f64 = group.iter().map(|(w, _)| w).sum();
+        if total > 0.0 {
+            files.extend(group.into_iter().map(|(w, path)| (share * w / total, path)));
+        }
+    }
+
+    let total: f64 = files.iter().map(|(w, _)| w).sum();
+    if total <= 0.0 {
+        let paths = sources.iter().map(|s| s.path.clone()).collect();
+        return Err(Error::EmptyCollection(paths));
+    }
+
+    Ok(files
+        .into_iter()
+        .map(|(w, path)| (w / total, path))
+        .collect())
+}
+
+/// Selects a random file from the given sources and returns its path.
+///
+/// See [`probabilities`] for how percentages and `strategy` combine.
+///
+/// # Errors
+///
+/// Same as [`probabilities`].
+pub fn pick_file_from(sources: &[Source], strategy: Strategy) -> Result<PathBuf> {
+    pick_file_from_with(&mut thread_rng(), sources, strategy)
+}
+
+/// Selects a random file from the given sources using `rng`, and returns its path.
+///
+/// Like [`pick_file_from`], but the same `rng` state always picks the same
+/// file from the same files.
+///
+/// # Errors
+///
+/// Same as [`probabilities`].
+pub fn pick_file_from_with<R: Rng + ?Sized>(
+    rng: &mut R,
+    sources: &[Source],
+    strategy: Strategy,
+) -> Result<PathBuf> {
+    let files = probabilities(sources, strategy)?;
+
+    let selected_file = files
+        .choose_weighted(rng, |item| item.0)
+        .expect("probabilities are never all zero");
+
+    Ok(selected_file.1.clone())
+}
+
+/// Selects a random file from the given paths, weighted by `strategy`, and returns its path.
+///
+/// All files under every path are pooled into a single weighted selection, so
+/// a larger collection is not disadvantaged by being listed alongside a smaller one.
+///
+/// # Arguments
+///
+/// * `paths` - The directories (or single files) to pick from.
+/// * `strategy` - How the files are weighted against each other.
+///
+/// # Returns
+///
+/// A `Result<PathBuf>` containing the path of the selected file.
+///
+/// # Errors
+///
+/// * Returns [`Error::NotFound`] (or another I/O variant) if a path cannot be read.
+/// * Returns [`Error::EmptyCollection`] if no file has a non-zero weight.
+pub fn pick_file(paths: &[PathBuf], strategy: Strategy) -> Result<PathBuf> {
+    let sources: Vec<Source> = paths.iter().cloned().map(Source::from).collect();
+    pick_file_from(&sources, strategy)
+}
+
+/// Selects a random file from the given paths, weighted by file size, and returns its path.
 ///
 /// Files with larger byte sizes have a statistically higher chance of being selected.
-/// This matches the behavior of the original `fortune` implementation.
+/// This matches the behavior of the original `fortune` implementation, and is
+/// shorthand for [`pick_file`] with [`Strategy::BySize`].
+///
+/// # Errors
+///
+/// Same as [`pick_file`].
+pub fn pick_file_weighted(paths: &[PathBuf]) -> Result<PathBuf> {
+    pick_file(paths, Strategy::BySize)
+}
+
+/// Selects a random file from the given paths, weighted by file size, and returns its contents.
+///
+/// See [`pick_file_weighted`] for how the file is chosen.
 ///
 /// # Arguments
 ///
-/// * `path` - A `PathBuf` pointing to the directory (or single file) to read from.
+/// * `paths` - The directories (or single files) to read from.
 ///
 /// # Returns
 ///
-/// A `std::io::Result<String>` containing the full text of the selected file.
+/// A `Result<String>` containing the full text of the selected file.
 ///
 /// # Errors
 ///
-/// * Returns `std::io::ErrorKind::NotFound` if the path does not exist.
-/// * Panics on other I/O errors or if the directory contains no valid files.
-pub fn get_random_file_weighted(path: PathBuf) -> std::io::Result<String> {
-    use std::io::ErrorKind;
-    let mut rng = thread_rng();
-
-    match get_file_sizes(&path) {
-        Ok(mut files) => {
-            // Ensure stable sorting for the weighted picker
-            files.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-
-            let selected_file = files
-                .choose_weighted(&mut rng, |item| item.0)
-                .map_err(|_| {
-                    std::io::Error::new(
-                        ErrorKind::Other,
-                        format!("No valid files found in {:?}", path),
-                    )
-                })?;
-
-            let mut contents = String::new();
-            std::fs::File::open(&selected_file.1)?.read_to_string(&mut contents)?;
-            Ok(contents)
-        }
-        Err(e) => match e.kind() {
-            ErrorKind::NotFound => {
-                eprintln!("Error: The path {:?} does not exist.", path);
-                std::process::exit(1);
-            }
-            _ => panic!("Unexpected I/O Error: {}", e),
-        },
-    }
+/// Same as [`pick_file_weighted`], plus any error from reading the selected file.
+pub fn get_random_file_weighted(paths: &[PathBuf]) -> Result<String> {
+    read_file(&pick_file_weighted(paths)?)
 }
 
 #[cfg(test)]
@@ -122,9 +606,28 @@ mod tests {
         random(0);
     }
 
+    /// Returns the share of picks landing on files whose name starts with
+    /// `letter`, picking once with each of many seeds.
+    ///
+    /// Only the distribution is checked, never which file a given seed picks,
+    /// since `StdRng` may produce other values in future versions of `rand`.
+    fn share(sources: &[Source], strategy: Strategy, letter: char) -> f64 {
+        const SEEDS: u64 = 2000;
+        let hits = (0..SEEDS)
+            .filter(|&seed| {
+                let path = pick_file_from_with(&mut seeded(seed), sources, strategy).unwrap();
+                path.file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with(letter)
+            })
+            .count();
+        hits as f64 / SEEDS as f64
+    }
+
     /// Tests weighted file selection using a temporary directory.
-    /// It creates one tiny file and one large file, then asserts that
-    /// after many iterations, the large file is picked significantly more often.
+    /// It creates one tiny file and one large file, and checks that the large
+    /// file gets almost every pick.
     #[test]
     fn test_weighted_selection_logic() {
         let dir = tempdir().unwrap();
@@ -139,24 +642,9 @@ mod tests {
         // 500 bytes
         large_file.write_all(&vec![b'a'; 500]).unwrap();
 
-        let mut large_picks = 0;
-        let iterations = 100;
-
-        for _ in 0..iterations {
-            let content = get_random_file_weighted(dir.path().to_path_buf()).unwrap();
-            if content.len() == 500 {
-                large_picks += 1;
-            }
-        }
-
-        // Statistically, the 500-byte file should be picked ~99% of the time.
-        // We check if it was picked at least 90 times out of 100 to account for rare variance.
-        assert!(
-            large_picks > 90,
-            "Weighted selection failed: Large file only picked {}/{} times",
-            large_picks,
-            iterations
-        );
+        let sources = [Source::from(dir.path().to_path_buf())];
+        // 500 : 5 bytes
+        assert!(share(&sources, Strategy::BySize, 'l') > 0.95);
     }
 
     /// Tests that get_random_file_weighted works correctly when pointed at a single file.
@@ -167,7 +655,241 @@ mod tests {
         let mut file = File::create(&path).unwrap();
         file.write_all(b"sole content").unwrap();
 
-        let result = get_random_file_weighted(path).unwrap();
+        let result = get_random_file_weighted(&[path]).unwrap();
         assert_eq!(result, "sole content");
     }
+
+    /// Tests that files from several paths are pooled into one weighted selection,
+    /// rather than picking a path first and a file second.
+    #[test]
+    fn test_weighted_selection_across_paths() {
+        let small_dir = tempdir().unwrap();
+        let mut small_file = File::create(small_dir.path().join("small.txt")).unwrap();
+        small_file.write_all(b"small").unwrap();
+
+        let large_dir = tempdir().unwrap();
+        let mut large_file = File::create(large_dir.path().join("large.txt")).unwrap();
+        large_file.write_all(&vec![b'a'; 500]).unwrap();
+
+        let sources = [
+            Source::from(small_dir.path().to_path_buf()),
+            Source::from(large_dir.path().to_path_buf()),
+        ];
+
+        // Picking a directory first would give the large file only half the picks.
+        assert!(share(&sources, Strategy::BySize, 'l') > 0.95);
+    }
+
+    /// Tests that missing paths and empty collections surface as typed errors.
+    #[test]
+    fn test_selection_errors() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        assert!(matches!(
+            get_random_file_weighted(&[missing]),
+            Err(Error::NotFound(_))
+        ));
+
+        File::create(dir.path().join("empty")).unwrap();
+        assert!(matches!(
+            get_random_file_weighted(&[dir.path().to_path_buf()]),
+            Err(Error::EmptyCollection(_))
+        ));
+    }
+
+    /// Tests that each strategy weights a file of one long fortune against a
+    /// file of many short ones as documented.
+    #[test]
+    fn test_strategies() {
+        let dir = tempdir().unwrap();
+        // One fortune, 500 bytes
+        let mut long = File::create(dir.path().join("long")).unwrap();
+        long.write_all(&vec![b'a'; 500]).unwrap();
+        // Ten fortunes, 60 bytes
+        let mut many = File::create(dir.path().join("many")).unwrap();
+        many.write_all("abc\n%\n".repeat(10).as_bytes()).unwrap();
+
+        let sources = [Source::from(dir.path().to_path_buf())];
+
+        let near = |strategy, expected: f64| {
+            let long = share(&sources, strategy, 'l');
+            assert!((long - expected).abs() < 0.05, "{strategy:?}: {long}");
+        };
+        // 500 : 60 bytes
+        near(Strategy::BySize, 500.0 / 560.0);
+        // 1 : 1 files
+        near(Strategy::EqualFiles, 0.5);
+        // 1 : 10 fortunes
+        near(Strategy::EqualFortunes, 1.0 / 11.0);
+    }
+
+    /// Tests the weight each strategy assigns to a single file.
+    #[test]
+    fn test_strategy_weight() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("quotes");
+        std::fs::write(&path, "One\n%\nTwo\n%\n").unwrap();
+
+        assert_eq!(Strategy::BySize.weight(12, &path).unwrap(), 12);
+        assert_eq!(Strategy::EqualFiles.weight(12, &path).unwrap(), 1);
+        assert_eq!(Strategy::EqualFiles.weight(0, &path).unwrap(), 0);
+        assert_eq!(Strategy::EqualFortunes.weight(12, &path).unwrap(), 2);
+
+        // Delimiters alone are not fortunes
+        std::fs::write(&path, "\n%\n").unwrap();
+        assert_eq!(Strategy::EqualFiles.weight(3, &path).unwrap(), 0);
+    }
+
+    /// Tests that fixed percentages are honoured and the remainder is split by size.
+    #[test]
+    fn test_probabilities_with_percentages() {
+        let dir = tempdir().unwrap();
+        for (name, size) in [("a", 100), ("b", 300), ("c", 100)] {
+            File::create(dir.path().join(name))
+                .unwrap()
+                .write_all(&vec![b'x'; size])
+                .unwrap();
+        }
+        let path = |name: &str| dir.path().join(name);
+        let prob = |files: &[(f64, PathBuf)], name: &str| {
+            files.iter().find(|(_, p)| *p == path(name)).unwrap().0
+        };
+
+        let sources = Source::parse_args([
+            format!("40%{}", path("a").display()),
+            path("b").display().to_string(),
+            path("c").display().to_string(),
+        ])
+        .unwrap();
+        let files = probabilities(&sources, Strategy::BySize).unwrap();
+
+        assert!((prob(&files, "a") - 0.40).abs() < 1e-9);
+        assert!((prob(&files, "b") - 0.45).abs() < 1e-9);
+        assert!((prob(&files, "c") - 0.15).abs() < 1e-9);
+    }
+
+    /// Tests that percentages are scaled when every source has one.
+    #[test]
+    fn test_probabilities_all_fixed() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join("a"))
+            .unwrap()
+            .write_all(b"a")
+            .unwrap();
+        File::create(dir.path().join("b"))
+            .unwrap()
+            .write_all(b"bbbb")
+            .unwrap();
+
+        let sources = [
+            Source {
+                percent: Some(10.0),
+                ..Source::from(dir.path().join("a"))
+            },
+            Source {
+                percent: Some(30.0),
+                ..Source::from(dir.path().join("b"))
+            },
+        ];
+        let files = probabilities(&sources, Strategy::BySize).unwrap();
+
+        assert!((files[0].0 - 0.25).abs() < 1e-9);
+        assert!((files[1].0 - 0.75).abs() < 1e-9);
+    }
+
+    /// Tests that a seeded generator always picks the same file.
+    #[test]
+    fn test_seeded_pick_is_reproducible() {
+        let dir = tempdir().unwrap();
+        for name in ["a", "b", "c", "d"] {
+            std::fs::write(dir.path().join(name), "x").unwrap();
+        }
+        let sources = [Source::from(dir.path().to_path_buf())];
+        let picks = |seed| -> Vec<PathBuf> {
+            let mut rng = seeded(seed);
+            (0..20)
+                .map(|_| pick_file_from_with(&mut rng, &sources, Strategy::BySize).unwrap())
+                .collect()
+        };
+
+        assert_eq!(picks(7), picks(7));
+        assert_ne!(picks(7), picks(8));
+    }
+
+    /// Tests conversion of days since the epoch into dates.
+    #[test]
+    fn test_date_from_days() {
+        assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days(19_782).to_string(), "2024-02-29");
+        assert_eq!(Date::from_days(-1).to_string(), "1969-12-31");
+    }
+
+    /// Tests that malformed and impossible dates are rejected.
+    #[test]
+    fn test_date_parse_errors() {
+        for bad in [
+            "2024-13-01",
+            "2024-04-31",
+            "2024-1-01",
+            "24-01-01",
+            "2024-01",
+            "today",
+        ] {
+            assert!(
+                matches!(bad.parse::<Date>(), Err(Error::BadDate(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    /// Tests the validation of percentage arguments.
+    #[test]
+    fn test_parse_args_errors() {
+        let bad = |args: &[&str]| matches!(Source::parse_args(args), Err(Error::BadWeights(_)));
+
+        assert!(bad(&["70%", "a", "40%", "b"]));
+        assert!(bad(&["150%", "a"]));
+        assert!(bad(&["30%", "40%", "a"]));
+        assert!(bad(&["a", "30%"]));
+        assert!(!bad(&["30%", "a", "70%b"]));
+        // Not a percentage, just a path with a % in it
+        assert_eq!(
+            Source::parse_args(["odd%name"]).unwrap()[0].path,
+            PathBuf::from("odd%name")
+        );
+    }
+
+    /// Tests that shadowing compares paths relative to each source, so
+    /// nested files only hide files at the same place.
+    #[test]
+    fn test_shadow_compares_relative_paths() {
+        let (user, system) = (tempdir().unwrap(), tempdir().unwrap());
+        for dir in ["sub", "other"] {
+            std::fs::create_dir(system.path().join(dir)).unwrap();
+        }
+        std::fs::create_dir(user.path().join("sub")).unwrap();
+        std::fs::write(user.path().join("sub/fedi"), "Mine\n%\n").unwrap();
+        std::fs::write(system.path().join("sub/fedi"), "Packaged\n%\n").unwrap();
+        std::fs::write(system.path().join("other/fedi"), "Unrelated\n%\n").unwrap();
+        std::fs::write(system.path().join("fedi"), "Top\n%\n").unwrap();
+
+        let recursive = |path: &Path| Source {
+            max_depth: usize::MAX,
+            ..Source::from(path.to_path_buf())
+        };
+        let mut sources = [recursive(user.path()), recursive(system.path())];
+        shadow(&mut sources).unwrap();
+        assert!(sources[1].excludes(&system.path().join("sub/fedi")));
+        assert!(!sources[1].excludes(&system.path().join("other/fedi")));
+        assert!(!sources[1].excludes(&system.path().join("fedi")));
+
+        // A file given as a source shadows files of its name at the top
+        let mut sources = [
+            Source::from(user.path().join("sub/fedi")),
+            recursive(system.path()),
+        ];
+        shadow(&mut sources).unwrap();
+        assert!(sources[1].excludes(&system.path().join("fedi")));
+        assert!(!sources[1].excludes(&system.path().join("sub/fedi")));
+    }
 }
@@ -0,0 +1,199 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for ROT13 encoded fortune files.
+//!
+//! Like `fortune-mod`, offensive collections may be stored rotated so their
+//! contents do not turn up in an accidental `grep`. Rotated files are decoded
+//! transparently whenever they are read.
+use crate::file;
+use crate::strfile;
+use crate::{Error, Result};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The extension marking a file of ROT13 encoded fortunes.
+pub const EXTENSION: &str = "rot13";
+
+/// Returns `true` if `path` holds ROT13 encoded fortunes.
+///
+/// A file is considered rotated if its name ends in `.rot13`, or if its `.dat`
+/// index has the [`STR_ROTATED`](strfile::STR_ROTATED) flag set, as written by
+/// `strfile -x`.
+pub fn is_rotated(path: &Path) -> bool {
+    has_extension(path)
+        || strfile::read_flags(path).is_some_and(|flags| flags & strfile::STR_ROTATED != 0)
+}
+
+/// Returns `true` if the file name of `path` ends in `.rot13`.
+pub fn has_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+/// Applies ROT13 to `text`. Applying it twice gives back the original.
+///
+/// Only ASCII letters are rotated, so byte offsets are preserved.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::rot13::rotate;
+///
+/// assert_eq!(rotate("Shit Happens."), "Fuvg Unccraf.");
+/// assert_eq!(rotate(&rotate("Shit Happens.")), "Shit Happens.");
+/// ```
+pub fn rotate(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'a'..='z' => ((c as u8 - b'a' + 13) % 26 + b'a') as char,
+            'A'..='Z' => ((c as u8 - b'A' + 13) % 26 + b'A') as char,
+            _ => c,
+        })
+        .collect()
+}
+
+/// Rotates or unrotates the fortune file at `path`, replacing it.
+///
+/// A plain file `fedi` is encoded into `fedi.rot13`, and `fedi.rot13` is
+/// decoded back into `fedi`, so the plain text is never left next to its
+/// rotated copy. A file only flagged as rotated by its `.dat` index is
+/// decoded in place. The `.dat` index of `path`, which no longer describes
+/// any file, is removed.
+///
+/// The result is written to a hidden file first and renamed into place, so
+/// the original is only removed once the result is complete.
+///
+/// Files that are not UTF-8 are decoded as for
+/// [`encoding::decode`](crate::encoding::decode), and written as UTF-8.
+///
+/// # Returns
+///
+/// The path of the file written.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if `path` cannot be read or the result cannot be
+/// written, or if another file already exists where the result would go,
+/// which is left untouched.
+pub fn toggle(path: &Path) -> Result<PathBuf> {
+    // Rotated files are decoded while reading, so `contents` is plain text
+    let (contents, _) = file::read_file_decoded(path)?;
+
+    let (out, contents) = if has_extension(path) {
+        (path.with_extension(""), contents)
+    } else if is_rotated(path) {
+        (path.to_path_buf(), contents)
+    } else {
+        (append_extension(path, EXTENSION), rotate(&contents))
+    };
+
+    if out != path && fs::symlink_metadata(&out).is_ok() {
+        return Err(Error::from_io(&out, io::ErrorKind::AlreadyExists.into()));
+    }
+    let tmp = temp_path(&out);
+    fs::write(&tmp, contents).map_err(|e| Error::from_io(&tmp, e))?;
+    if let Err(e) = fs::rename(&tmp, &out) {
+        let _ = fs::remove_file(&tmp);
+        return Err(Error::from_io(&out, e));
+    }
+    if out != path {
+        fs::remove_file(path).map_err(|e| Error::from_io(path, e))?;
+    }
+    let dat = strfile::dat_path(path);
+    match fs::remove_file(&dat) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::from_io(&dat, e)),
+        _ => Ok(out),
+    }
+}
+
+/// Returns the hidden file `.<name>.tmp` next to `path`, which fortune
+/// directories skip while it is being written.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Returns `path` with `.ext` appended to its file name.
+fn append_extension(path: &Path, ext: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::read_file;
+
+    #[test]
+    fn test_rotate_skips_non_letters() {
+        assert_eq!(rotate("Hello, World! æ 42"), "Uryyb, Jbeyq! æ 42");
+    }
+
+    #[test]
+    fn test_toggle_round_trip() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let plain = tmp_dir.path().join("cookie");
+        fs::write(&plain, "Shit Happens.\n%\n").unwrap();
+
+        let rotated = toggle(&plain).unwrap();
+        assert_eq!(rotated, tmp_dir.path().join("cookie.rot13"));
+        assert_eq!(fs::read_to_string(&rotated).unwrap(), "Fuvg Unccraf.\n%\n");
+        // Reading decodes transparently
+        assert_eq!(read_file(&rotated).unwrap(), "Shit Happens.\n%\n");
+
+        // The plain text is replaced, not kept next to the rotated copy
+        assert!(!plain.exists());
+
+        assert_eq!(toggle(&rotated).unwrap(), plain);
+        assert_eq!(fs::read_to_string(&plain).unwrap(), "Shit Happens.\n%\n");
+        assert!(!rotated.exists());
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_toggle_keeps_existing_files() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let plain = tmp_dir.path().join("cookie");
+        let rotated = tmp_dir.path().join("cookie.rot13");
+        fs::write(&plain, "Mine.\n%\n").unwrap();
+        fs::write(&rotated, "Fuvg Unccraf.\n%\n").unwrap();
+
+        assert!(matches!(toggle(&rotated), Err(Error::AlreadyExists(p)) if p == plain));
+        assert!(matches!(toggle(&plain), Err(Error::AlreadyExists(p)) if p == rotated));
+        assert_eq!(fs::read_to_string(&plain).unwrap(), "Mine.\n%\n");
+        assert_eq!(fs::read_to_string(&rotated).unwrap(), "Fuvg Unccraf.\n%\n");
+        assert_eq!(fs::read_dir(tmp_dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_toggle_flagged_file_in_place() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("cookie");
+        fs::write(&path, "Fuvg Unccraf.\n%\n").unwrap();
+        let mut index = strfile::write_index(&path).unwrap();
+        index.flags |= strfile::STR_ROTATED;
+        fs::write(strfile::dat_path(&path), index.to_bytes()).unwrap();
+
+        assert_eq!(toggle(&path).unwrap(), path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Shit Happens.\n%\n");
+        assert!(!strfile::dat_path(&path).exists());
+    }
+
+    #[test]
+    fn test_toggle_latin1() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let plain = tmp_dir.path().join("cookie");
+        fs::write(&plain, b"Caf\xe9.\n%\n").unwrap();
+
+        let rotated = toggle(&plain).unwrap();
+        assert_eq!(fs::read_to_string(&rotated).unwrap(), "Pnsé.\n%\n");
+    }
+}
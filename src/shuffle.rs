@@ -0,0 +1,256 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for cycling through every fortune of a collection before repeating.
+//!
+//! A shuffle bag is a random permutation of the fortunes in a collection and a
+//! cursor into it. Each pick takes the fortune under the cursor and advances
+//! it, and a new permutation is drawn once the cursor reaches the end, so
+//! every fortune is shown exactly once per round.
+//!
+//! Bags are stored as JSON in [`state_dir`](crate::history::state_dir), one per
+//! set of sources. A bag is thrown away when any file of its collection
+//! changes size or modification time, or the files excluded from it change,
+//! since its fortunes may have moved.
+use crate::file;
+use crate::fortune::{read_all_fortunes, Fortune};
+use crate::history;
+use crate::random::{self, Source};
+use crate::{Error, Result};
+
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// A shuffled order of the fortunes in a collection, and how far through it we are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bag {
+    /// A fingerprint of the size and modification time of every file in the
+    /// collection when the bag was shuffled, see [`stamp`].
+    pub stamp: u64,
+    /// The fortunes of the collection, as positions in the order they are read.
+    pub order: Vec<usize>,
+    /// The position in `order` of the next fortune to show.
+    pub cursor: usize,
+}
+
+impl Bag {
+    /// Loads the bag stored at `path`.
+    ///
+    /// A missing or malformed file is an empty bag, which is reshuffled on
+    /// first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] naming `path` if it exists but cannot be read.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json).unwrap_or_default()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Bag::default()),
+            Err(e) => Err(Error::from_io(path, e)),
+        }
+    }
+
+    /// Writes the bag to `path`, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] naming `path` if it cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::from_io(dir, e))?;
+        }
+        let json = serde_json::to_string(self).expect("bags always serialize to JSON");
+        fs::write(path, json).map_err(|e| Error::from_io(path, e))
+    }
+
+    /// Returns the position of the next fortune to show, out of `len`.
+    ///
+    /// The bag is reshuffled with `rng` first if it is used up, or if it was
+    /// shuffled for a collection other than the one `stamp` describes.
+    fn next<R: Rng + ?Sized>(&mut self, rng: &mut R, stamp: u64, len: usize) -> usize {
+        if self.stamp != stamp || self.order.len() != len || self.cursor >= len {
+            self.stamp = stamp;
+            self.order = (0..len).collect();
+            self.order.shuffle(rng);
+            self.cursor = 0;
+        }
+
+        self.cursor += 1;
+        self.order[self.cursor - 1]
+    }
+}
+
+/// Returns a fingerprint of the size and modification time of every file under
+/// `sources`, and of the names each source excludes.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if any of the sources cannot be read.
+pub fn stamp(sources: &[Source]) -> Result<u64> {
+    let mut summary = String::new();
+    for source in sources {
+        summary.push_str(&format!(
+            "{}\0{}\n",
+            source.path.display(),
+            source.exclude.join("\0")
+        ));
+        for (size, modified, path) in file::get_file_stamps(&source.path, source.max_depth)? {
+            if source.excludes(&path) {
+                continue;
+            }
+            let nanos = modified
+                .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos());
+            summary.push_str(&format!("{}\0{}\0{}\n", path.display(), size, nanos));
+        }
+    }
+    Ok(random::fnv1a(summary.as_bytes()))
+}
+
+/// Returns where the bag for `sources` is kept, inside [`history::state_dir`].
+///
+/// Each distinct set of sources, depths and excluded names has its own bag,
+/// so `fortune-kind fedi` and `fortune-kind -a` cycle independently. Returns
+/// `None` if there is no state directory.
+pub fn bag_path(sources: &[Source]) -> Option<PathBuf> {
+    let key: String = sources
+        .iter()
+        .map(|s| {
+            let exclude = s.exclude.join("\0");
+            format!("{}\0{}\0{}\n", s.path.display(), s.max_depth, exclude)
+        })
+        .collect();
+    let name = format!("{:016x}.json", random::fnv1a(key.as_bytes()));
+    history::state_dir().map(|dir| dir.join("bags").join(name))
+}
+
+/// Picks the next fortune from the shuffle bag for `sources`, stored at `path`.
+///
+/// Uses [`thread_rng`] to shuffle, see [`next_with`].
+///
+/// # Errors
+///
+/// Same as [`next_with`].
+pub fn next(sources: &[Source], path: &Path) -> Result<Fortune> {
+    next_with(&mut thread_rng(), sources, path)
+}
+
+/// Picks the next fortune from the shuffle bag for `sources`, stored at `path`,
+/// shuffling with `rng` when a new round starts.
+///
+/// Percentages on the sources are ignored, since every fortune is shown once
+/// per round regardless. The command line rejects them along with `--shuffle`.
+///
+/// # Errors
+///
+/// * An I/O variant of [`Error`] if the sources or the bag cannot be read, or
+///   the bag cannot be written.
+/// * [`Error::EmptyCollection`] if the sources contain no fortunes.
+///
+/// # Examples
+///
+/// ```
+/// use fortune_kind::shuffle;
+/// use std::collections::HashSet;
+/// use std::fs;
+/// use tempfile::tempdir;
+///
+/// let dir = tempdir().unwrap();
+/// fs::write(dir.path().join("quotes"), "One\n%\nTwo\n%\nThree\n%\n").unwrap();
+/// let sources = [dir.path().to_path_buf().into()];
+/// let state = tempdir().unwrap();
+/// let bag = state.path().join("bag.json");
+///
+/// let round: HashSet<String> = (0..3)
+///     .map(|_| shuffle::next(&sources, &bag).unwrap().text)
+///     .collect();
+/// assert_eq!(round.len(), 3);
+/// ```
+pub fn next_with<R: Rng + ?Sized>(rng: &mut R, sources: &[Source], path: &Path) -> Result<Fortune> {
+    let stamp = stamp(sources)?;
+    let mut fortunes = read_all_fortunes(sources)?;
+    if fortunes.is_empty() {
+        return Err(Error::EmptyCollection(
+            sources.iter().map(|s| s.path.clone()).collect(),
+        ));
+    }
+
+    let mut bag = Bag::load(path)?;
+    let i = bag.next(rng, stamp, fortunes.len());
+    bag.save(path)?;
+
+    Ok(fortunes.swap_remove(i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_bag_cycles_before_repeating() {
+        let mut bag = Bag::default();
+        let mut rng = random::seeded(1);
+
+        for _ in 0..3 {
+            let mut round: Vec<usize> = (0..5).map(|_| bag.next(&mut rng, 7, 5)).collect();
+            round.sort();
+            assert_eq!(round, [0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn test_bag_reshuffles_when_collection_changes() {
+        let dir = tempdir().unwrap();
+        let quotes = dir.path().join("quotes");
+        fs::write(&quotes, "One\n%\nTwo\n%\n").unwrap();
+        let sources = [Source::from(quotes.clone())];
+        let path = dir.path().join("bag.json");
+
+        next(&sources, &path).unwrap();
+        assert_eq!(Bag::load(&path).unwrap().cursor, 1);
+
+        fs::write(&quotes, "One\n%\nTwo\n%\nThree\n%\n").unwrap();
+        next(&sources, &path).unwrap();
+        let bag = Bag::load(&path).unwrap();
+        assert_eq!((bag.cursor, bag.order.len()), (1, 3));
+    }
+
+    #[test]
+    fn test_bag_depends_on_exclude() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("quotes"), "One\n%\n").unwrap();
+        fs::write(dir.path().join("ascii-art"), "Two\n%\n").unwrap();
+        let all = [Source::from(dir.path().to_path_buf())];
+        let some = [Source {
+            exclude: vec!["ascii-art".to_string()],
+            ..Source::from(dir.path().to_path_buf())
+        }];
+
+        assert_ne!(stamp(&all).unwrap(), stamp(&some).unwrap());
+        // Excluding a name that matches nothing still starts a new bag
+        let none = [Source {
+            exclude: vec!["nothing".to_string()],
+            ..Source::from(dir.path().to_path_buf())
+        }];
+        assert_ne!(stamp(&all).unwrap(), stamp(&none).unwrap());
+        if history::state_dir().is_some() {
+            assert_ne!(bag_path(&all), bag_path(&some));
+        }
+    }
+
+    #[test]
+    fn test_load_ignores_malformed_bag() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bag.json");
+        fs::write(&path, "not json").unwrap();
+
+        assert_eq!(Bag::load(&path).unwrap(), Bag::default());
+    }
+}
@@ -0,0 +1,381 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! A module for reading and writing strfile-compatible `.dat` index files.
+//!
+//! The layout matches the one written by `strfile(8)` from `fortune-mod`: a
+//! 24 byte header followed by one offset per fortune plus a final offset
+//! pointing at the end of the file, all as big-endian 32 bit integers.
+//!
+//! With an index present, a random fortune can be read by seeking straight
+//! to its offset instead of scanning the whole file.
+use crate::collection;
+use crate::file;
+use crate::parser;
+use crate::rot13;
+use crate::{Error, Result};
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// The header version written by `fortune-mod`'s strfile.
+pub const VERSION: u32 = 2;
+
+/// Fortunes are stored in random order.
+pub const STR_RANDOM: u32 = 0x1;
+/// Fortunes are stored in alphabetical order.
+pub const STR_ORDERED: u32 = 0x2;
+/// Fortunes are ROT13 encoded.
+pub const STR_ROTATED: u32 = 0x4;
+/// Lines starting with a doubled delimiter are comments.
+pub const STR_COMMENTS: u32 = 0x8;
+
+/// The size of the header in bytes.
+const HEADER_LEN: usize = 24;
+
+/// An offset table for a single fortune file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    /// The header version, `1` for BSD strfile and `2` for `fortune-mod`.
+    pub version: u32,
+    /// The length in bytes of the longest fortune.
+    pub longlen: u32,
+    /// The length in bytes of the shortest fortune.
+    pub shortlen: u32,
+    /// A combination of the `STR_*` flags.
+    pub flags: u32,
+    /// The delimiter character, always `%` for fortune-kind collections.
+    pub delim: u8,
+    /// The byte offset of each fortune, followed by the length of the file.
+    pub offsets: Vec<u32>,
+}
+
+impl Index {
+    /// Builds an index for the raw contents of a fortune file.
+    ///
+    /// The fortunes indexed are exactly those the library would read from the
+    /// file, so fortune `i` of the index is fortune `i` of the file. Offsets
+    /// and lengths count bytes as stored, even if the file is not UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fortune_kind::strfile::Index;
+    ///
+    /// let index = Index::build(b"One\n%\nTwo\n%\n");
+    /// assert_eq!(index.len(), 2);
+    /// assert_eq!(index.offsets, [0, 6, 12]);
+    /// ```
+    pub fn build(contents: &[u8]) -> Self {
+        let mut offsets = vec![];
+        let mut longlen = 0;
+        let mut shortlen = u32::MAX;
+
+        for entry in parser::Parser::new(contents) {
+            let entry = entry.expect("reading from memory cannot fail");
+            // strfile counts the newline ending the last line of the fortune
+            let len = entry.len as u32 + 1;
+            longlen = longlen.max(len);
+            shortlen = shortlen.min(len);
+            offsets.push(entry.offset as u32);
+        }
+        offsets.push(contents.len() as u32);
+
+        Index {
+            version: VERSION,
+            longlen,
+            shortlen: if offsets.len() > 1 { shortlen } else { 0 },
+            flags: 0,
+            delim: b'%',
+            offsets,
+        }
+    }
+
+    /// Returns the number of fortunes in the index.
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    /// Returns `true` if the index contains no fortunes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Serializes the index in the strfile binary layout.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 4 * self.offsets.len());
+        bytes.extend(self.version.to_be_bytes());
+        bytes.extend((self.len() as u32).to_be_bytes());
+        bytes.extend(self.longlen.to_be_bytes());
+        bytes.extend(self.shortlen.to_be_bytes());
+        bytes.extend(self.flags.to_be_bytes());
+        bytes.extend([self.delim, 0, 0, 0]);
+        for offset in &self.offsets {
+            bytes.extend(offset.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Parses an index from the strfile binary layout.
+    ///
+    /// Returns `None` if `bytes` is truncated or has an unknown version.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let word = |i: usize| -> Option<u32> {
+            let b = bytes.get(i * 4..i * 4 + 4)?;
+            Some(u32::from_be_bytes(b.try_into().ok()?))
+        };
+
+        let version = word(0)?;
+        if version != 1 && version != 2 {
+            return None;
+        }
+        let numstr = word(1)? as usize;
+        let offsets = (0..=numstr)
+            .map(|i| word(HEADER_LEN / 4 + i))
+            .collect::<Option<Vec<u32>>>()?;
+
+        Some(Index {
+            version,
+            longlen: word(2)?,
+            shortlen: word(3)?,
+            flags: word(4)?,
+            delim: bytes[20],
+            offsets,
+        })
+    }
+
+    /// Reads the raw text of fortune `i` from `source`, the file this index describes.
+    ///
+    /// ROT13 encoded fortunes are decoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] naming `source` if it cannot be read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn fetch(&self, source: &Path, i: usize) -> Result<String> {
+        let (start, end) = (self.offsets[i], self.offsets[i + 1]);
+        let io_err = |e| Error::from_io(source, e);
+
+        let mut file = fs::File::open(source).map_err(io_err)?;
+        file.seek(SeekFrom::Start(start.into())).map_err(io_err)?;
+        let mut buf = vec![0; end.saturating_sub(start) as usize];
+        file.read_exact(&mut buf).map_err(io_err)?;
+
+        let text = String::from_utf8(buf).map_err(|_| Error::InvalidUtf8(source.to_path_buf()))?;
+        if self.flags & STR_ROTATED != 0 || rot13::has_extension(source) {
+            return Ok(rot13::rotate(&text));
+        }
+        Ok(text)
+    }
+}
+
+/// Returns the path of the index file for `source`, i.e. `source.dat`.
+pub fn dat_path(source: &Path) -> PathBuf {
+    let mut name = source.as_os_str().to_owned();
+    name.push(".dat");
+    PathBuf::from(name)
+}
+
+/// Builds and writes the index for the fortune file at `source`.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if `source` cannot be read or the index cannot be written.
+pub fn write_index(source: &Path) -> Result<Index> {
+    // ROT13 leaves the layout of a file alone, so the raw bytes are indexed
+    let mut index = Index::build(&collection::of(source).read(source)?);
+    if rot13::is_rotated(source) {
+        index.flags |= STR_ROTATED;
+    }
+    let dat = dat_path(source);
+    fs::write(&dat, index.to_bytes()).map_err(|e| Error::from_io(&dat, e))?;
+    Ok(index)
+}
+
+/// Builds and writes the index for every fortune file under `path`.
+///
+/// Subdirectories are indexed up to `max_depth` levels deep, as for
+/// [`Source::max_depth`](crate::random::Source::max_depth).
+///
+/// Returns the path of each fortune file indexed, along with its index.
+///
+/// # Errors
+///
+/// Returns an [`Error`] on the first file that cannot be read or indexed.
+pub fn write_indexes(path: &Path, max_depth: usize) -> Result<Vec<(PathBuf, Index)>> {
+    file::walk(path, max_depth)?
+        .into_iter()
+        .map(|source| write_index(&source).map(|index| (source, index)))
+        .collect()
+}
+
+/// Reads the flags of the index for `source`, even if the index is stale.
+///
+/// Returns `None` if there is no index, or its header is malformed.
+pub fn read_flags(source: &Path) -> Option<u32> {
+    let mut header = [0; HEADER_LEN];
+    fs::File::open(dat_path(source))
+        .ok()?
+        .read_exact(&mut header)
+        .ok()?;
+    Some(u32::from_be_bytes(header[16..20].try_into().ok()?))
+}
+
+/// Loads the index for `source`, if one exists and is up to date.
+///
+/// An index is considered stale if it is older than `source`, or if its final
+/// offset no longer matches the length of `source`. Indexes written by
+/// `strfile -r` or `-o` list their fortunes out of file order, so they cannot
+/// be read by position either. Stale, missing, reordered or malformed indexes
+/// all yield `None`, so callers can fall back to scanning.
+pub fn read_index(source: &Path) -> Option<Index> {
+    let dat = dat_path(source);
+    let source_meta = fs::metadata(source).ok()?;
+    let dat_meta = fs::metadata(&dat).ok()?;
+
+    if dat_meta.modified().ok()? < source_meta.modified().ok()? {
+        return None;
+    }
+
+    let index = Index::from_bytes(&fs::read(&dat).ok()?)?;
+    if index.offsets.last().copied() != Some(source_meta.len() as u32) {
+        return None;
+    }
+    if index.flags & (STR_RANDOM | STR_ORDERED) != 0 {
+        return None;
+    }
+
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_build_skips_empty_entries() {
+        let index = Index::build(b"One\n%\n\n%\nThree\n%\n");
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.offsets, [0, 9, 17]);
+        assert_eq!(index.longlen, 6);
+        assert_eq!(index.shortlen, 4);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let index = Index::build(b"One\n%\nTwo\n%\n");
+        let bytes = index.to_bytes();
+
+        assert_eq!(bytes.len(), HEADER_LEN + 4 * 3);
+        // Header fields are big-endian, as strfile writes them
+        assert_eq!(&bytes[..8], &[0, 0, 0, 2, 0, 0, 0, 2]);
+        assert_eq!(bytes[20], b'%');
+        assert_eq!(Index::from_bytes(&bytes), Some(index));
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_truncated() {
+        let bytes = Index::build(b"One\n%\nTwo\n%\n").to_bytes();
+
+        assert_eq!(Index::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Index::from_bytes(&[]), None);
+    }
+
+    #[test]
+    fn test_write_read_fetch() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("quotes");
+        let mut file = File::create(&path).unwrap();
+        write!(file, "One\n%\nTwo\nlines\n%\n").unwrap();
+
+        write_index(&path).unwrap();
+        let index = read_index(&path).unwrap();
+
+        assert!(index.fetch(&path, 1).unwrap().starts_with("Two\nlines\n"));
+    }
+
+    #[test]
+    fn test_index_counts_file_bytes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("oldtunes");
+        let contents = b"Caf\xe9 au lait.\n%\nNa\xefve.\n%\n";
+        fs::write(&path, contents).unwrap();
+
+        let index = write_index(&path).unwrap();
+        assert_eq!(index.offsets, [0, 16, contents.len() as u32]);
+        assert_eq!((index.longlen, index.shortlen), (14, 7));
+        assert_eq!(read_index(&path), Some(index));
+    }
+
+    #[test]
+    fn test_read_index_detects_stale() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("quotes");
+        fs::write(&path, "One\n%\n").unwrap();
+        write_index(&path).unwrap();
+
+        // Same mtime granularity is possible, so rely on the length check.
+        fs::write(&path, "One\n%\nTwo\n%\n").unwrap();
+        assert_eq!(read_index(&path), None);
+    }
+
+    #[test]
+    fn test_read_index_rejects_reordered() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("quotes");
+        fs::write(&path, "Zebra\n%\nApple\n%\n").unwrap();
+
+        // Sort the fortunes, like `strfile -o` would
+        let mut index = Index::build(b"Zebra\n%\nApple\n%\n");
+        index.offsets.swap(0, 1);
+        index.flags |= STR_ORDERED;
+        fs::write(dat_path(&path), index.to_bytes()).unwrap();
+        assert_eq!(read_index(&path), None);
+
+        index.flags = STR_RANDOM;
+        fs::write(dat_path(&path), index.to_bytes()).unwrap();
+        assert_eq!(read_index(&path), None);
+
+        // The file is scanned instead
+        let options = crate::fortune::QuoteOptions::default();
+        for _ in 0..10 {
+            let fortune = crate::fortune::get_quote(&options, std::slice::from_ref(&path)).unwrap();
+            assert!(["Zebra", "Apple"].contains(&fortune.text.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_dat_path() {
+        assert_eq!(dat_path(Path::new("a/fedi")), PathBuf::from("a/fedi.dat"));
+    }
+
+    #[test]
+    fn test_rotated_flag() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cookie");
+        fs::write(&path, "Fuvg Unccraf.\n%\n").unwrap();
+
+        // Mark the file as rotated, like `strfile -x` would
+        let mut index = Index::build(b"Fuvg Unccraf.\n%\n");
+        index.flags |= STR_ROTATED;
+        fs::write(dat_path(&path), index.to_bytes()).unwrap();
+
+        assert_eq!(read_flags(&path), Some(STR_ROTATED));
+        assert_eq!(file::read_file(&path).unwrap(), "Shit Happens.\n%\n");
+
+        // Reindexing keeps the flag
+        assert_eq!(write_index(&path).unwrap().flags, STR_ROTATED);
+        let index = read_index(&path).unwrap();
+        assert_eq!(index.fetch(&path, 0).unwrap(), "Shit Happens.\n%\n");
+    }
+}
@@ -0,0 +1,640 @@
+// SPDX-FileCopyrightText: 2026 Clare K. Tam
+// SPDX-FileContributor: Clare K. Tam
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use assert_cmd::Command;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Helper to create a temporary fortune file with the given contents.
+fn fortune_file(content: &str) -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("quotes");
+    let mut file = File::create(&path).unwrap();
+    write!(file, "{}", content).unwrap();
+    (dir, path)
+}
+
+/// Runs the binary, ignoring any configuration, search path or installed
+/// collections of the user running the tests.
+fn fortune_kind() -> Command {
+    let mut cmd = Command::cargo_bin("fortune-kind").unwrap();
+    cmd.env("XDG_CONFIG_HOME", "/nonexistent")
+        .env("XDG_DATA_HOME", "/nonexistent")
+        .env("XDG_DATA_DIRS", "/nonexistent")
+        .env_remove("FORTUNE_PATH");
+    cmd
+}
+
+#[test]
+fn test_length_only_picks_short_enough() {
+    let (_dir, path) = fortune_file("Tiny\n%\nThis one is far too long to fit\n%\n");
+
+    for _ in 0..10 {
+        fortune_kind()
+            .args(["-n", "10"])
+            .arg(&path)
+            .assert()
+            .success()
+            .stdout("Tiny\n");
+    }
+}
+
+#[test]
+fn test_length_errors_when_nothing_fits() {
+    let (_dir, path) = fortune_file("This one is far too long to fit\n%\n");
+
+    let assert = fortune_kind()
+        .args(["-n", "5"])
+        .arg(&path)
+        .assert()
+        .failure()
+        .stdout("");
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("at most 5 characters"), "{stderr}");
+}
+
+#[test]
+fn test_length_combines_with_find() {
+    let (_dir, path) = fortune_file("Linux\n%\nLinux is a long fortune\n%\nMac\n%\n");
+
+    fortune_kind()
+        .args(["-m", "Linux", "-n", "10"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("Linux\n%\n");
+}
+
+#[test]
+fn test_all_draws_from_kind_and_unkind() {
+    let kind = tempfile::tempdir().unwrap();
+    let mut file = File::create(kind.path().join("kind")).unwrap();
+    write!(file, "Kind\n%\n").unwrap();
+
+    let unkind = tempfile::tempdir().unwrap();
+    let mut file = File::create(unkind.path().join("unkind")).unwrap();
+    write!(file, "Unkind\n%\n").unwrap();
+
+    let mut seen = std::collections::HashSet::new();
+    for _ in 0..50 {
+        let output = fortune_kind()
+            .arg("--all")
+            .env("FORTUNE_DIR", kind.path())
+            .env("FORTUNE_OFF_DIR", unkind.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        seen.insert(String::from_utf8(output.stdout).unwrap());
+    }
+
+    assert!(seen.contains("Kind\n"), "{seen:?}");
+    assert!(seen.contains("Unkind\n"), "{seen:?}");
+}
+
+#[test]
+fn test_exit_codes_distinguish_failures() {
+    let (dir, path) = fortune_file("Linux\n%\n");
+
+    // No match
+    fortune_kind()
+        .args(["-m", "Windows"])
+        .arg(&path)
+        .assert()
+        .code(1);
+
+    // Broken install
+    fortune_kind()
+        .arg(dir.path().join("missing"))
+        .assert()
+        .code(66);
+
+    // Empty collection
+    let empty = tempfile::tempdir().unwrap();
+    File::create(empty.path().join("empty")).unwrap();
+    fortune_kind().arg(empty.path()).assert().code(65);
+}
+
+#[test]
+fn test_find_is_a_regex() {
+    let (_dir, path) = fortune_file("Linux\n%\nlinux\n%\nMac\n%\n");
+
+    fortune_kind()
+        .args(["-m", "^L.n"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("Linux\n%\n");
+
+    fortune_kind()
+        .args(["-i", "-m", "^L.n"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("Linux\n%\nlinux\n%\n");
+
+    fortune_kind()
+        .args(["-m", "(Linux"])
+        .arg(&path)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_index_writes_dat_files() {
+    let (dir, _path) = fortune_file("One\n%\nTwo\n%\n");
+
+    fortune_kind()
+        .arg("index")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(format!("{:?}: 2 fortunes\n", dir.path().join("quotes.dat")));
+
+    assert!(dir.path().join("quotes.dat").exists());
+
+    // The index itself must never be picked as a fortune
+    for _ in 0..10 {
+        let output = fortune_kind().arg(dir.path()).output().unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout == "One\n" || stdout == "Two\n", "{stdout:?}");
+    }
+}
+
+#[test]
+fn test_percentages_pick_sources() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("always"), "Always\n%\n").unwrap();
+    // Much bigger, so size weighting alone would almost always pick it
+    std::fs::write(dir.path().join("never"), "Never\n%\n".repeat(100)).unwrap();
+
+    for _ in 0..10 {
+        fortune_kind()
+            .arg("100%")
+            .arg(dir.path().join("always"))
+            .arg(dir.path().join("never"))
+            .assert()
+            .success()
+            .stdout("Always\n");
+    }
+
+    fortune_kind()
+        .args(["60%", "a", "50%", "b"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_list_shows_counts_and_odds() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a"), "One\n%\n").unwrap();
+    std::fs::write(dir.path().join("b"), "One\n%\nTwo\n%\nThree\n%\n").unwrap();
+    let dir_path = std::fs::canonicalize(dir.path()).unwrap();
+
+    fortune_kind()
+        .args(["--list", "--equal-files"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(format!(
+            " 50.00%      1  {}\n 50.00%      3  {}\n",
+            dir_path.join("a").display(),
+            dir_path.join("b").display()
+        ));
+}
+
+#[test]
+fn test_recursive_reads_nested_collections() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("off").join("unrotated");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(nested.join("deep"), "Deep\n%\n").unwrap();
+
+    fortune_kind().arg(dir.path()).assert().code(65);
+    fortune_kind()
+        .args(["--max-depth", "2"])
+        .arg(dir.path())
+        .assert()
+        .code(65);
+    fortune_kind()
+        .arg("-r")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout("Deep\n");
+}
+
+#[test]
+fn test_rot13_files_are_decoded() {
+    let (dir, path) = fortune_file("Shit Happens.\n%\n");
+
+    fortune_kind().arg("rot13").arg(&path).assert().success();
+    let rotated = dir.path().join("quotes.rot13");
+    assert_eq!(
+        std::fs::read_to_string(&rotated).unwrap(),
+        "Fuvg Unccraf.\n%\n"
+    );
+    assert!(!path.exists());
+
+    fortune_kind()
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout("Shit Happens.\n");
+}
+
+#[test]
+fn test_author_and_no_attribution() {
+    let (_dir, path) = fortune_file("Kind words.\n    -- Someone\n%\nOther words.\n%\n");
+
+    fortune_kind()
+        .args(["--author", "SOMEONE", "--no-attribution"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("Kind words.\n");
+    fortune_kind()
+        .args(["--author", "Nobody"])
+        .arg(&path)
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn test_json_formats() {
+    let (_dir, path) = fortune_file("Kind words.\n    -- Someone\n%\nOther words.\n%\n");
+
+    let output = fortune_kind()
+        .args(["--format", "json", "--author", "someone"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let fortune: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(fortune["body"], "Kind words.");
+    assert_eq!(fortune["attribution"], "Someone");
+    assert_eq!(fortune["index"], 0);
+
+    let output = fortune_kind()
+        .args(["--format", "ndjson", "-m", "words"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    let lines: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["text"], "Other words.");
+    assert_eq!(lines[1]["length"], 12);
+}
+
+#[test]
+fn test_seed_is_reproducible() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a", "b", "c"] {
+        let content: String = (0..10).map(|i| format!("{name}{i}\n%\n")).collect();
+        std::fs::write(dir.path().join(name), content).unwrap();
+    }
+    let pick = |seed: &str| {
+        let output = fortune_kind()
+            .args(["--seed", seed])
+            .arg(dir.path())
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(pick("1"), pick("1"));
+    let picks: std::collections::HashSet<String> =
+        (0..10).map(|seed| pick(&seed.to_string())).collect();
+    assert!(picks.len() > 1);
+}
+
+#[test]
+fn test_date_picks_are_stable() {
+    let dir = tempfile::tempdir().unwrap();
+    let content: String = (0..50).map(|i| format!("Fortune {i}\n%\n")).collect();
+    std::fs::write(dir.path().join("quotes"), content).unwrap();
+    let pick = |args: &[&str]| {
+        let output = fortune_kind().args(args).arg(dir.path()).output().unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let day = pick(&["--date", "2024-05-04"]);
+    assert_eq!(day, pick(&["--date", "2024-05-04"]));
+    let salted: std::collections::HashSet<String> = (0..10)
+        .map(|i| pick(&["--date", "2024-05-04", "--salt", &i.to_string()]))
+        .collect();
+    assert!(salted.len() > 1);
+
+    fortune_kind()
+        .args(["--date", "2024-02-30"])
+        .arg(dir.path())
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_no_repeat_window() {
+    let state = tempfile::tempdir().unwrap();
+    let (_dir, path) = fortune_file("One\n%\nTwo\n%\nThree\n%\n");
+    let pick = || {
+        let output = fortune_kind()
+            .env("XDG_STATE_HOME", state.path())
+            .args(["--no-repeat-window", "3"])
+            .arg(&path)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let mut shown: Vec<String> = (0..3).map(|_| pick()).collect();
+    shown.sort();
+    assert_eq!(shown, ["One\n", "Three\n", "Two\n"]);
+    assert!(state.path().join("fortune-kind/history").exists());
+
+    fortune_kind()
+        .env("XDG_STATE_HOME", state.path())
+        .args(["history", "clear"])
+        .assert()
+        .success();
+    assert!(!state.path().join("fortune-kind/history").exists());
+}
+
+#[test]
+fn test_shuffle_shows_every_fortune_once() {
+    let state = tempfile::tempdir().unwrap();
+    let (_dir, path) = fortune_file("One\n%\nTwo\n%\nThree\n%\nFour\n%\n");
+    let pick = || {
+        let output = fortune_kind()
+            .env("XDG_STATE_HOME", state.path())
+            .arg("--shuffle")
+            .arg(&path)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    for _ in 0..2 {
+        let mut round: Vec<String> = (0..4).map(|_| pick()).collect();
+        round.sort();
+        assert_eq!(round, ["Four\n", "One\n", "Three\n", "Two\n"]);
+    }
+
+    // Every fortune is shown once, so percentages make no sense
+    fortune_kind()
+        .env("XDG_STATE_HOME", state.path())
+        .args(["--shuffle", "50%"])
+        .arg(&path)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_config_file() {
+    let config = tempfile::tempdir().unwrap();
+    let collection = tempfile::tempdir().unwrap();
+    std::fs::write(collection.path().join("kept"), "Kept\n    -- Someone\n%\n").unwrap();
+    std::fs::write(collection.path().join("ascii-art"), "Skipped\n%\n").unwrap();
+    std::fs::create_dir(config.path().join("fortune-kind")).unwrap();
+    std::fs::write(
+        config.path().join("fortune-kind/config.toml"),
+        format!(
+            "paths = [{:?}]\nexclude = [\"ascii-art\"]\nformat = \"ndjson\"\ncolor = \"always\"\n",
+            collection.path()
+        ),
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = fortune_kind()
+            .env("XDG_CONFIG_HOME", config.path())
+            .env_remove("FORTUNE_DIR")
+            .env_remove("NO_COLOR")
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    for _ in 0..5 {
+        assert!(run(&[]).starts_with("{\"text\":\"Kept"));
+    }
+    // Flags win over the config file
+    assert_eq!(
+        run(&["--format", "plain"]),
+        "Kept\n\x1b[2m    -- Someone\x1b[0m\n"
+    );
+    assert_eq!(
+        run(&["--format", "plain", "--color", "never"]),
+        "Kept\n    -- Someone\n"
+    );
+
+    // A config short can be turned off with -n 0
+    let long = "x".repeat(200);
+    std::fs::write(
+        collection.path().join("kept"),
+        format!("Hi\n%\n{long}\n%\n"),
+    )
+    .unwrap();
+    std::fs::write(
+        config.path().join("fortune-kind/config.toml"),
+        format!(
+            "paths = [{:?}]\nexclude = [\"ascii-art\"]\nshort = 1\n",
+            collection.path()
+        ),
+    )
+    .unwrap();
+    let seeds: Vec<String> = (0..20).map(|seed| seed.to_string()).collect();
+    assert!(seeds.iter().all(|s| run(&["--seed", s]) == "Hi\n"));
+    assert!(seeds
+        .iter()
+        .any(|s| run(&["--seed", s, "-n", "0"]) == format!("{long}\n")));
+
+    std::fs::write(
+        config.path().join("fortune-kind/config.toml"),
+        "short = \"yes\"",
+    )
+    .unwrap();
+    fortune_kind()
+        .env("XDG_CONFIG_HOME", config.path())
+        .assert()
+        .code(78);
+
+    // Maintenance subcommands that are given paths do not need the config
+    let (_dir, path) = fortune_file("Uryyb\n%\n");
+    fortune_kind()
+        .env("XDG_CONFIG_HOME", config.path())
+        .args(["lint"])
+        .arg(&path)
+        .assert()
+        .success();
+    fortune_kind()
+        .env("XDG_CONFIG_HOME", config.path())
+        .args(["rot13"])
+        .arg(&path)
+        .assert()
+        .success();
+    fortune_kind()
+        .env("XDG_CONFIG_HOME", config.path())
+        .env("XDG_STATE_HOME", config.path())
+        .args(["history", "clear"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_fortune_path_shadows_by_name() {
+    let user = tempfile::tempdir().unwrap();
+    let system = tempfile::tempdir().unwrap();
+    std::fs::write(user.path().join("fedi"), "Mine\n%\n").unwrap();
+    std::fs::write(system.path().join("fedi"), "Packaged\n%\n").unwrap();
+    std::fs::write(system.path().join("pratchett"), "Other\n%\n").unwrap();
+    let search_path = std::env::join_paths([
+        user.path(),
+        std::path::Path::new("/nonexistent"),
+        system.path(),
+    ])
+    .unwrap();
+
+    let output = fortune_kind()
+        .env("FORTUNE_PATH", &search_path)
+        .env("FORTUNE_DIR", "/nonexistent")
+        .args(["-m", ""])
+        .output()
+        .unwrap();
+    let found = String::from_utf8(output.stdout).unwrap();
+    assert!(found.contains("Mine"));
+    assert!(found.contains("Other"));
+    assert!(!found.contains("Packaged"));
+}
+
+#[test]
+fn test_data_dirs() {
+    let data = tempfile::tempdir().unwrap();
+    let installed = data.path().join("fortune-kind").join("fortunes");
+    std::fs::create_dir_all(&installed).unwrap();
+    std::fs::write(installed.join("local"), "Installed by hand\n%\n").unwrap();
+
+    fortune_kind()
+        .env("XDG_DATA_HOME", data.path())
+        .env_remove("FORTUNE_DIR")
+        .args(["-m", "Installed by hand"])
+        .assert()
+        .success()
+        .stdout("Installed by hand\n%\n");
+}
+
+#[cfg(feature = "embedded-fortunes")]
+#[test]
+fn test_embedded_fortunes() {
+    let output = fortune_kind()
+        .env_remove("FORTUNE_DIR")
+        .current_dir(std::env::temp_dir())
+        .arg("--list")
+        .output()
+        .unwrap();
+    let listed = String::from_utf8(output.stdout).unwrap();
+    assert!(listed.contains("<embedded>/fortunes/fedi"));
+
+    fortune_kind()
+        .arg("<embedded>/fortunes/fedi")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_lint() {
+    let (_dir, clean) = fortune_file("One\n%\nTwo\n%\n");
+    fortune_kind()
+        .arg("lint")
+        .arg(&clean)
+        .assert()
+        .success()
+        .stdout("");
+
+    let (_dir, broken) = fortune_file("One\n% \nTwo \n%\n");
+    let output = fortune_kind().arg("lint").arg(&broken).output().unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("quotes:2: error: "));
+    assert!(stdout.contains("quotes:3: warning: trailing whitespace [trailing-whitespace]"));
+
+    let (_dir, untidy) = fortune_file("One \n%\nTwo\n%\n");
+    fortune_kind().arg("lint").arg(&untidy).assert().success();
+    let output = fortune_kind()
+        .args(["lint", "--deny-warnings", "--format", "ndjson"])
+        .arg(&untidy)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\"severity\":\"warning\""));
+}
+
+#[test]
+fn test_fmt() {
+    let (dir, path) = fortune_file("One \r\n    -- Someone\r\n\r\n%\r\nTwo");
+    let art = dir.path().join("ascii-art");
+    std::fs::write(&art, " o \n/|\\ \n%\n").unwrap();
+
+    let output = fortune_kind()
+        .args(["fmt", "--diff"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    let diff = String::from_utf8(output.stdout).unwrap();
+    assert!(diff.contains("+\t\t-- Someone\n"));
+    assert!(!diff.contains("ascii-art"));
+
+    fortune_kind().arg("fmt").arg(dir.path()).assert().success();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "One\n\t\t-- Someone\n%\nTwo\n%\n"
+    );
+    assert_eq!(std::fs::read_to_string(&art).unwrap(), " o \n/|\\ \n%\n");
+    fortune_kind()
+        .args(["fmt", "--check"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_latin1_fallback_and_convert() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("oldtunes");
+    std::fs::write(&path, b"Caf\xe9 au lait.\n%\n").unwrap();
+
+    let output = fortune_kind().arg(&path).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Café au lait.\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("oldtunes:1: not valid UTF-8, read as Windows-1252"));
+
+    let output = fortune_kind()
+        .args(["convert", "--check"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("oldtunes:1: Windows-1252"));
+
+    fortune_kind()
+        .arg("convert")
+        .arg(dir.path())
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "Café au lait.\n%\n"
+    );
+    fortune_kind().arg(&path).assert().success().stderr("");
+}